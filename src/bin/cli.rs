@@ -0,0 +1,475 @@
+//! Command-line modes for the Othello engine.
+//!
+//! Usage:
+//!     othello-cli bot-vs-human <depth> [white|black] [time_ms]
+//!     othello-cli bot-vs-bot <depth> [time_ms]
+//!     othello-cli random <n_games>
+//!     othello-cli bench <depth> [tt_size_mb] [ordering] [evaluator]
+//!     othello-cli match <depth> <evaluator> <evaluator>
+//!     othello-cli perft <depth> [position]
+//!     othello-cli solve <position> [exact|wld]
+//!     othello-cli explain <position>
+//!     othello-cli train <weights_file> <source>...
+//!
+//! `ordering` is `all`, `none` or a comma-separated list of `hash`,
+//! `mobility`, `priors` and `history`. `evaluator` is `disc`, `squares`,
+//! `mobility`, `classical`, `pattern` for the built-in pattern weights or
+//! `pattern:<file>` for a weight file. `time_ms` caps each bot move at
+//! that many milliseconds on top of the depth limit.
+//!
+//! `solve` finds the exact final score by default, or with `wld` only
+//! whether the side to move wins, draws or loses. `explain` shows the
+//! classical evaluation terms of a position and of every legal move.
+//!
+//! `train` fits pattern weights and writes them to `weights_file`. Each
+//! source is a `.wtb` or `.ggf` game file or `self-play:<games>[:<depth>]`.
+//! Training starts from the weights already in `weights_file` if there are
+//! any and from the built-in weights otherwise. Self-play uses the same
+//! weights, or the classical evaluator if the file does not exist yet.
+
+#![allow(clippy::needless_return)]
+
+use std::io;
+use std::time::Duration;
+
+use rand::Rng;
+
+use othello::classical::N_TERMS;
+use othello::{
+    fit_pattern_weights, perft_divide, samples_from_game, self_play, GgfGame, TrainingConfig, WthorReader, Board, ClassicalEvaluator, DiscCountEvaluator, EndgameSolver, Evaluator, Game, GameStatus, MobilityEvaluator, Move, MoveGenerator,
+    MoveOrdering, PatternEvaluator, Search, SearchLimits, Term, TermScore, WeightedSquareEvaluator, WeightsError, BLACK,
+    WHITE,
+};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mode = args.get(1).map(|s| s.as_str());
+    let arg = |i: usize, default: u32| -> u32 {
+        return args.get(i).and_then(|s| s.parse().ok()).unwrap_or(default);
+    };
+    let limits = |depth_arg: usize, time_arg: usize| -> SearchLimits {
+        let mut limits = SearchLimits::depth(arg(depth_arg, 9));
+        limits.time = args.get(time_arg).and_then(|s| s.parse().ok()).map(Duration::from_millis);
+        return limits;
+    };
+
+    match mode {
+        Some("bot-vs-human") => {
+            let play_white = args.get(3).map(|s| s == "white").unwrap_or(false);
+            bot_vs_human(limits(2, 4), play_white);
+        }
+        Some("bot-vs-bot") => bot_vs_bot(limits(2, 3)),
+        Some("random") => gen_random_games(arg(2, 1000)),
+        Some("bench") => {
+            let ordering = parse_ordering(args.get(4).map(|s| s.as_str()).unwrap_or("all"));
+            let evaluator = parse_evaluator(args.get(5).map(|s| s.as_str()).unwrap_or("disc"));
+            bench(arg(2, 9), arg(3, 16) as usize, ordering, evaluator);
+        }
+        Some("match") => match (args.get(3), args.get(4)) {
+            (Some(first), Some(second)) => run_match(arg(2, 6), [first, second]),
+            _ => {
+                eprintln!("usage: othello-cli match <depth> <evaluator> <evaluator>");
+                std::process::exit(1);
+            }
+        },
+        Some("perft") => {
+            let position = args.get(3).map(|s| s.as_str());
+            run_perft(arg(2, 8), position);
+        }
+        Some("solve") => match args.get(2) {
+            Some(position) => {
+                let wld = args.get(3).map(|s| s == "wld").unwrap_or(false);
+                solve(parse_position(position), wld);
+            }
+            None => {
+                eprintln!("usage: othello-cli solve <position> [exact|wld]");
+                std::process::exit(1);
+            }
+        },
+        Some("train") => match args.get(2) {
+            Some(output) if args.len() > 3 => train(output, &args[3..]),
+            _ => {
+                eprintln!("usage: othello-cli train <weights_file> <source>...");
+                std::process::exit(1);
+            }
+        },
+        Some("explain") => match args.get(2) {
+            Some(position) => explain(parse_position(position)),
+            None => {
+                eprintln!("usage: othello-cli explain <position>");
+                std::process::exit(1);
+            }
+        },
+        _ => {
+            eprintln!("usage: othello-cli <bot-vs-human|bot-vs-bot|random|bench|match|perft|solve|explain|train> [args]");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn print_result(status: GameStatus) {
+    if let GameStatus::Finished { winner, score } = status {
+        println!("Game finished!");
+        match winner {
+            Some(WHITE) => println!("White wins with {} pieces to {}!\n", score[WHITE], score[BLACK]),
+            Some(_) => println!("Black wins with {} pieces to {}!\n", score[BLACK], score[WHITE]),
+            None => println!("Draw!\n"),
+        }
+    }
+}
+
+fn bot_vs_human(limits: SearchLimits, play_white: bool) {
+    let mut player_side = WHITE;
+    if !play_white {
+        player_side = BLACK;
+    }
+    let mut game = Game::std_start_pos();
+    let move_generator = MoveGenerator::new();
+    let mut bot = Search::new(game.get_board(), move_generator);
+    let stdio = std::io::stdin();
+    loop {
+        let board = game.get_board();
+        print!("{}", board.to_grid_string());
+        println!("{} {}", board.get_num_pieces(WHITE), board.get_num_pieces(BLACK));
+        let status = board.get_game_status();
+        if let GameStatus::Finished { .. } = status {
+            print_result(status);
+            println!("{}", game.to_transcript());
+            break;
+        }
+        if board.get_side_to_move() == player_side {
+            if status == GameStatus::MustPass {
+                println!("No legal moves, passing");
+                game.play(Move::pass(player_side)).unwrap();
+                continue;
+            }
+            for mv in move_generator.legal_move_iter(&board) {
+                print!("{} ", mv);
+            }
+            println!();
+            let mut line = String::new();
+            if stdio.read_line(&mut line).unwrap() == 0 {
+                break;
+            }
+            let mv = match Move::parse(&line, player_side) {
+                Ok(mv) => mv,
+                Err(err) => {
+                    println!("{}", err);
+                    continue;
+                }
+            };
+            if let Err(err) = game.play(mv) {
+                println!("Illegal move: {}", err);
+            }
+        }
+        else {
+            bot.find_best_move_with_limits(board, limits);
+            if let Some(mv) = bot.get_best_move() {
+                match bot.get_outcome() {
+                    Some(outcome) => println!("Bot plays {} (solved: {})", mv, outcome),
+                    None => println!("Bot plays {} (depth {})", mv, bot.get_completed_depth()),
+                }
+                game.play(mv).unwrap();
+            }
+        }
+    }
+}
+
+fn bot_vs_bot(limits: SearchLimits) {
+    let mut game = Game::std_start_pos();
+    let move_generator = MoveGenerator::new();
+    let mut bot = Search::new(game.get_board(), move_generator);
+    loop {
+        let board = game.get_board();
+        print!("{}", board.to_grid_string());
+        bot.find_best_move_with_limits(board, limits);
+        let mv = match bot.get_best_move() {
+            Some(mv) => mv,
+            None => {
+                print_result(board.get_game_status());
+                println!("{}", game.to_transcript());
+                break;
+            }
+        };
+        println!("{} {}", mv, board.get_side_to_move());
+        game.play(mv).unwrap();
+    }
+}
+
+fn gen_random_games(n_games: u32) {
+    let move_generation = MoveGenerator::new();
+    let mut rng = rand::thread_rng();
+
+    let mut w_wins = 0;
+    let mut b_wins = 0;
+    let mut draws = 0;
+
+    for _ in 0..n_games {
+        let mut board = Board::std_start_pos(WHITE);
+        loop {
+            if let GameStatus::Finished { winner, .. } = board.get_game_status() {
+                match winner {
+                    Some(WHITE) => w_wins += 1,
+                    Some(_) => b_wins += 1,
+                    None => draws += 1,
+                }
+                break;
+            }
+            let mut moves = move_generation.legal_move_iter(&board);
+            let random_number = rng.gen_range(0..moves.len());
+            board.make_move(moves.nth(random_number).unwrap());
+        }
+    }
+    println!("Finished with {} wins for white, {} wins for black and {} draws", w_wins, b_wins, draws);
+}
+
+// Openings the benchmark searches from, covering the early midgame.
+const BENCH_GAMES: [&str; 4] = [
+    "f5d6c3d3c4f4f6f3e6e7",
+    "f5f6e6f4e3c5c4d3",
+    "f5d6c3d3c4f4c5b3c2",
+    "c4e3f6e6f5c5c3",
+];
+
+fn parse_ordering(spec: &str) -> MoveOrdering {
+    match spec {
+        "all" => return MoveOrdering::all(),
+        "none" => return MoveOrdering::none(),
+        _ => {}
+    }
+    let mut ordering = MoveOrdering::none();
+    for name in spec.split(',') {
+        match name {
+            "hash" => ordering.hash_move = true,
+            "mobility" => ordering.mobility = true,
+            "priors" => ordering.square_priors = true,
+            "history" => ordering.history = true,
+            _ => {
+                eprintln!("unknown ordering heuristic '{}'", name);
+                std::process::exit(1);
+            }
+        }
+    }
+    return ordering;
+}
+
+fn parse_evaluator(spec: &str) -> Box<dyn Evaluator> {
+    match spec {
+        "disc" => return Box::new(DiscCountEvaluator),
+        "squares" => return Box::new(WeightedSquareEvaluator::default()),
+        "mobility" => return Box::new(MobilityEvaluator::default()),
+        "classical" => return Box::new(ClassicalEvaluator::default()),
+        "pattern" => return Box::new(PatternEvaluator::builtin()),
+        _ => {}
+    }
+    if let Some(path) = spec.strip_prefix("pattern:") {
+        match PatternEvaluator::load(path) {
+            Ok(evaluator) => return Box::new(evaluator),
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                std::process::exit(1);
+            }
+        }
+    }
+    eprintln!("unknown evaluator '{}'", spec);
+    std::process::exit(1);
+}
+
+fn bench(target_depth: u32, tt_size_mb: usize, ordering: MoveOrdering, evaluator: Box<dyn Evaluator>) {
+    let move_generator = MoveGenerator::new();
+    let mut bot = Search::new(Board::std_start_pos(BLACK), move_generator);
+    bot.set_move_ordering(ordering);
+    bot.set_evaluator(evaluator);
+    let mut total_nodes = 0;
+    let start = std::time::Instant::now();
+    for transcript in BENCH_GAMES {
+        let board = Game::from_transcript(transcript).unwrap().get_board();
+        bot.set_tt_size(tt_size_mb);
+        bot.find_best_move(board, target_depth);
+        total_nodes += bot.get_nodes();
+        println!("{} best {} nodes {}", transcript, bot.get_best_move().unwrap(), bot.get_nodes());
+    }
+    let secs = start.elapsed().as_secs_f64();
+    println!("{} nodes in {:.2}s ({:.0} nodes/s)", total_nodes, secs, total_nodes as f64 / secs);
+}
+
+// Plays every bench opening twice, once with each evaluator moving first,
+// and reports the results from the first evaluator's view.
+fn run_match(target_depth: u32, specs: [&String; 2]) {
+    let move_generator = MoveGenerator::new();
+    let mut bots = specs.map(|spec| {
+        let mut bot = Search::new(Board::std_start_pos(BLACK), move_generator);
+        bot.set_evaluator(parse_evaluator(spec));
+        return bot;
+    });
+    let (mut wins, mut losses, mut draws, mut discs) = (0, 0, 0, 0);
+    for transcript in BENCH_GAMES {
+        for first in 0..2 {
+            let mut game = Game::from_transcript(transcript).unwrap();
+            let first_side = game.get_board().get_side_to_move();
+            loop {
+                let board = game.get_board();
+                let bot = if board.get_side_to_move() == first_side { first } else { first ^ 1 };
+                bots[bot].find_best_move(board, target_depth);
+                match bots[bot].get_best_move() {
+                    Some(mv) => game.play(mv).unwrap(),
+                    None => break,
+                }
+            }
+            let mut score = 0;
+            if let GameStatus::Finished { score: final_score, .. } = game.get_board().get_game_status() {
+                let side = if first == 0 { first_side } else { first_side ^ 1 };
+                score = final_score[side] - final_score[side ^ 1];
+            }
+            match score {
+                s if s > 0 => wins += 1,
+                s if s < 0 => losses += 1,
+                _ => draws += 1,
+            }
+            discs += score;
+            println!("{} {:+}", game.to_transcript(), score);
+        }
+    }
+    println!("{} vs {}: {} wins, {} losses, {} draws, {:+} discs", specs[0], specs[1], wins, losses, draws, discs);
+}
+
+fn parse_position(position: &str) -> Board {
+    match position.parse::<Board>() {
+        Ok(board) => return board,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn run_perft(depth: u32, position: Option<&str>) {
+    let mut board = position.map(parse_position).unwrap_or(Board::std_start_pos(BLACK));
+    let start = std::time::Instant::now();
+    let mut total_nodes = 0;
+    for (mv, nodes) in perft_divide(&mut board, depth) {
+        println!("{} {}", mv, nodes);
+        total_nodes += nodes;
+    }
+    let secs = start.elapsed().as_secs_f64();
+    println!("perft {} = {} ({:.2}s, {:.0} nodes/s)", depth, total_nodes, secs, total_nodes as f64 / secs);
+}
+
+fn solve(board: Board, wld: bool) {
+    let mut solver = EndgameSolver::new(MoveGenerator::new());
+    let start = std::time::Instant::now();
+    let result = if wld {
+        let (best_move, outcome) = solver.solve_wld(&board).expect("solves without limits always finish");
+        (best_move, outcome.to_string())
+    }
+    else {
+        let (best_move, score) = solver.solve(&board);
+        (best_move, format!("score {:+}", score))
+    };
+    let secs = start.elapsed().as_secs_f64();
+    match result {
+        (Some(mv), result) => println!("best {} {}", mv, result),
+        (None, result) => println!("game over, {}", result),
+    }
+    println!("{} nodes in {:.2}s ({:.0} nodes/s)", solver.get_nodes(), secs, solver.get_nodes() as f64 / secs);
+}
+
+// Prints the classical terms of `board` and of the position after each
+// legal move, all from the side to move's view, as `value/score` pairs.
+fn explain(board: Board) {
+    let evaluator = ClassicalEvaluator::default();
+    let print_row = |label: String, breakdown: [TermScore; N_TERMS]| {
+        let total: i32 = breakdown.iter().map(|term| term.score).sum();
+        print!("{:<8}{:>8}", label, total);
+        for term in breakdown {
+            print!("{:>20}", format!("{}/{}", term.value, term.score));
+        }
+        println!();
+    };
+
+    print!("{:<8}{:>8}", "move", "total");
+    for term in Term::ALL {
+        print!("{:>20}", term.name());
+    }
+    println!();
+    print_row("now".to_string(), evaluator.breakdown(&board));
+    for mv in MoveGenerator::new().legal_move_iter(&board) {
+        let mut child = board;
+        child.make_move(mv);
+        let breakdown = evaluator.breakdown(&child).map(|term| TermScore { value: -term.value, score: -term.score, ..term });
+        print_row(mv.to_string(), breakdown);
+    }
+}
+
+fn train(output: &str, sources: &[String]) {
+    let mut games = vec![];
+    for source in sources {
+        let start = games.len();
+        if let Some(spec) = source.strip_prefix("self-play:") {
+            let mut parts = spec.split(':').map(|part| part.parse::<usize>());
+            let (n_games, depth) = match (parts.next(), parts.next()) {
+                (Some(Ok(n_games)), None) => (n_games, 4),
+                (Some(Ok(n_games)), Some(Ok(depth))) => (n_games, depth),
+                _ => {
+                    eprintln!("invalid self-play source '{}'", source);
+                    std::process::exit(1);
+                }
+            };
+            let mut bot = Search::new(Board::std_start_pos(BLACK), MoveGenerator::new());
+            match PatternEvaluator::load(output) {
+                Ok(evaluator) => bot.set_evaluator(Box::new(evaluator)),
+                Err(WeightsError::Io(err)) if err.kind() == io::ErrorKind::NotFound => {
+                    bot.set_evaluator(Box::new(ClassicalEvaluator::default()));
+                }
+                Err(err) => {
+                    eprintln!("{}: {}", output, err);
+                    std::process::exit(1);
+                }
+            }
+            let limits = SearchLimits::depth(depth as u32);
+            games.extend(self_play(&mut bot, n_games, limits, 8, &mut rand::thread_rng()));
+        }
+        else if source.ends_with(".wtb") {
+            let reader = WthorReader::open(source).unwrap_or_else(|err| {
+                eprintln!("{}: {}", source, err);
+                std::process::exit(1);
+            });
+            for game in reader {
+                match game {
+                    Ok(game) => games.push(game.game),
+                    Err(err) => eprintln!("{}: {}", source, err),
+                }
+            }
+        }
+        else {
+            let text = std::fs::read_to_string(source).unwrap_or_else(|err| {
+                eprintln!("{}: {}", source, err);
+                std::process::exit(1);
+            });
+            match GgfGame::parse_all(&text) {
+                Ok(ggf_games) => games.extend(ggf_games.into_iter().map(|game| game.game)),
+                Err(err) => {
+                    eprintln!("{}: {}", source, err);
+                    std::process::exit(1);
+                }
+            }
+        }
+        println!("{}: {} games", source, games.len() - start);
+    }
+
+    let samples: Vec<_> = games.iter().flat_map(samples_from_game).collect();
+    println!("{} positions from {} games", samples.len(), games.len());
+    let start = std::time::Instant::now();
+    let initial = PatternEvaluator::load_or_builtin(output).unwrap_or_else(|err| {
+        eprintln!("{}: {}", output, err);
+        std::process::exit(1);
+    });
+    let evaluator = fit_pattern_weights(&samples, &initial, &TrainingConfig::default(), |epoch, rmse| {
+        println!("epoch {} rmse {:.2} discs ({:.1}s)", epoch, rmse, start.elapsed().as_secs_f64());
+    });
+    if let Err(err) = evaluator.save(output) {
+        eprintln!("{}: {}", output, err);
+        std::process::exit(1);
+    }
+    println!("wrote {}", output);
+}
@@ -0,0 +1,922 @@
+//! Bitboard representation of an Othello position.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use crate::move_generator::MoveGenerator;
+use crate::moves::Move;
+use crate::symmetry::Symmetry;
+use crate::zobrist;
+
+/// Side index of the white player.
+pub const WHITE: usize = 0;
+/// Side index of the black player.
+pub const BLACK: usize = 1;
+
+/// Predefined bitboards for start positions and shift masks.
+#[repr(u64)]
+pub enum BoardPositions {
+    WStdStartPos = 0x1008000000,
+    BStdStartPos = 0x810000000,
+    
+
+    /// Every square except the a- and h-files. Discs on these files can
+    /// never lie between two others along a line with a sideways step,
+    /// and masking them stops shifts from wrapping between rows.
+    BitBoardInnerFiles = 0x7E7E7E7E7E7E7E7E,
+}
+
+/// Reasons `Board::try_make_move` can reject a move.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MoveError {
+    /// The square is not on the board.
+    OutOfRange,
+    /// The move is for the side that is not to move.
+    WrongSide,
+    /// The square already holds a disc.
+    Occupied,
+    /// The move would not flip any discs.
+    NoFlips,
+    /// The side to move has a legal placement and may not pass.
+    IllegalPass,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            MoveError::OutOfRange => "square is out of range",
+            MoveError::WrongSide => "it is not this side's turn",
+            MoveError::Occupied => "square is already occupied",
+            MoveError::NoFlips => "move does not flip any discs",
+            MoveError::IllegalPass => "cannot pass while a legal move exists",
+        };
+        return write!(f, "{}", msg);
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+/// Reasons a position string can fail to parse as a `Board`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseBoardError {
+    /// The board part does not have exactly 64 squares.
+    WrongLength(usize),
+    /// A square holds a character other than `X`, `O` or `-`.
+    InvalidSquare { index: usize, found: char },
+    /// The side to move is missing after the board part.
+    MissingSideToMove,
+    /// The side to move is neither `X` nor `O`.
+    InvalidSideToMove(String),
+    /// Unexpected text follows the side to move.
+    TrailingInput(String),
+}
+
+impl fmt::Display for ParseBoardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseBoardError::WrongLength(len) => write!(f, "expected 64 squares, found {}", len),
+            ParseBoardError::InvalidSquare { index, found } => {
+                write!(f, "invalid character '{}' on square {}, expected 'X', 'O' or '-'", found, index)
+            }
+            ParseBoardError::MissingSideToMove => write!(f, "missing side to move after the board"),
+            ParseBoardError::InvalidSideToMove(side) => {
+                write!(f, "invalid side to move '{}', expected 'X' or 'O'", side)
+            }
+            ParseBoardError::TrailingInput(extra) => write!(f, "unexpected '{}' after the side to move", extra),
+        }
+    }
+}
+
+impl std::error::Error for ParseBoardError {}
+
+/// State of the game as reported by `Board::get_game_status`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GameStatus {
+    /// The side to move has at least one placement.
+    Ongoing,
+    /// The side to move has no placement but the opponent does.
+    MustPass,
+    /// Neither side can move. `winner` is `None` for a draw and `score`
+    /// holds the final disc counts indexed by side, with empty squares
+    /// awarded to the winner.
+    Finished { winner: Option<usize>, score: [i32; 2] },
+}
+
+/// Everything `Board::unmake_move` needs to take back a move.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UndoInfo {
+    /// The move that was played.
+    pub mv: Move,
+    /// Bitboard of the discs flipped by the move.
+    pub flipped: u64,
+    /// Side to move before the move was played.
+    pub side_to_move: usize,
+    /// Position hash before the move was played.
+    pub hash: u64,
+}
+
+/// An Othello position: discs of both sides and the side to move.
+///
+/// Square `0` is the top-left corner and square `63` the bottom-right,
+/// counting row by row. Boards compare equal when they hold the same
+/// discs and side to move.
+#[derive(Copy, Clone, Debug)]
+pub struct Board {
+    occ_squares: [u64; 2],
+    side_to_move: usize,
+    hash: u64
+}
+
+impl Board {
+
+    /// Creates a board from the white and black disc bitboards.
+    pub fn new(w_start_pos: u64, b_start_pos: u64, side_to_move: usize) -> Board {
+        let occ_squares = [w_start_pos, b_start_pos];
+        let hash = zobrist::hash_position(occ_squares, side_to_move);
+
+        return Board { 
+            occ_squares,
+            side_to_move,
+            hash
+         };
+    }
+
+    /// Creates the standard start position with `side_to_move` to play.
+    pub fn std_start_pos(side_to_move: usize) -> Board {
+        return Board::new(BoardPositions::WStdStartPos as u64, BoardPositions::BStdStartPos as u64, side_to_move);
+    }
+
+    /// Plays `mv` on the board and returns the record needed to undo it.
+    /// Assumes legal move.
+    pub fn make_move(&mut self, mv: Move) -> UndoInfo {
+        let undo = UndoInfo {
+            mv,
+            flipped: 0,
+            side_to_move: self.side_to_move,
+            hash: self.hash,
+        };
+        let (square, side) = match mv {
+            Move::Place { square, side } => (square, side),
+            Move::Pass { .. } => {
+                self.switch_side_to_move();
+                return undo;
+            }
+        };
+
+        let captured_pieces = self.get_flips(square, side);
+        self.occ_squares[side ^ 1] ^= captured_pieces;
+        self.occ_squares[side] |= captured_pieces | (1 << square);
+        self.hash ^= zobrist::PIECE_KEYS[side][square as usize];
+        let mut flipped = captured_pieces;
+        while flipped != 0 {
+            self.hash ^= zobrist::flip_key(flipped.trailing_zeros());
+            flipped &= flipped - 1;
+        }
+        self.switch_side_to_move();
+        return UndoInfo { flipped: captured_pieces, ..undo };
+    }
+
+    /// Returns the discs of the opponent of `side` that a disc placed on
+    /// `square` would flip.
+    pub fn get_flips(&self, square: u32, side: usize) -> u64 {
+        let own = self.occ_squares[side];
+        let placed: u64 = 1 << square;
+        let mut flips = 0;
+        for direction in 0..8 {
+            let opp = self.occ_squares[side ^ 1] & get_dir_mask(direction);
+            let mut line = opp & self.shift_bitboard_in_dir(placed, direction);
+            line |= opp & self.shift_bitboard_in_dir(line, direction);
+            line |= opp & self.shift_bitboard_in_dir(line, direction);
+            line |= opp & self.shift_bitboard_in_dir(line, direction);
+            line |= opp & self.shift_bitboard_in_dir(line, direction);
+            line |= opp & self.shift_bitboard_in_dir(line, direction);
+            if own & self.shift_bitboard_in_dir(line, direction) != 0 {
+                flips |= line;
+            }
+        }
+        return flips;
+    }
+
+    /// Plays `mv` if it is legal for the side to move, otherwise leaves
+    /// the board untouched and returns why it was rejected.
+    pub fn try_make_move(&mut self, mv: Move) -> Result<UndoInfo, MoveError> {
+        if mv.get_side() != self.side_to_move {
+            return Err(MoveError::WrongSide);
+        }
+        let legal_moves = MoveGenerator::new().generate_legal_moves(self);
+        let square = match mv {
+            Move::Place { square, .. } => square,
+            Move::Pass { .. } => {
+                if legal_moves != 0 {
+                    return Err(MoveError::IllegalPass);
+                }
+                return Ok(self.make_move(mv));
+            }
+        };
+        if square > 63 {
+            return Err(MoveError::OutOfRange);
+        }
+        if self.get_occ_squares() & (1 << square) != 0 {
+            return Err(MoveError::Occupied);
+        }
+        if legal_moves & (1 << square) == 0 {
+            return Err(MoveError::NoFlips);
+        }
+        return Ok(self.make_move(mv));
+    }
+
+    /// Takes back the move recorded in `undo`, restoring the exact
+    /// position it was played from.
+    pub fn unmake_move(&mut self, undo: UndoInfo) {
+        if let Move::Place { square, side } = undo.mv {
+            self.occ_squares[side] ^= undo.flipped | (1 << square);
+            self.occ_squares[side ^ 1] |= undo.flipped;
+        }
+        self.side_to_move = undo.side_to_move;
+        self.hash = undo.hash;
+    }
+
+    /// Returns the board transformed by `symmetry`, with the same side to
+    /// move.
+    pub fn transform(&self, symmetry: Symmetry) -> Board {
+        let white = symmetry.apply_bitboard(self.occ_squares[WHITE]);
+        let black = symmetry.apply_bitboard(self.occ_squares[BLACK]);
+        return Board::new(white, black, self.side_to_move);
+    }
+
+    /// Returns the canonical representative among the symmetric variants
+    /// of the board, together with the symmetry that maps this board onto
+    /// it. Moves found on the canonical board map back to this board
+    /// through `symmetry.inverse()`.
+    pub fn canonical(&self) -> (Board, Symmetry) {
+        let mut best = (*self, Symmetry::Identity);
+        let mut best_key = (self.occ_squares[WHITE], self.occ_squares[BLACK]);
+        for symmetry in Symmetry::ALL.iter().skip(1) {
+            let key = (
+                symmetry.apply_bitboard(self.occ_squares[WHITE]),
+                symmetry.apply_bitboard(self.occ_squares[BLACK]),
+            );
+            if key < best_key {
+                best_key = key;
+                best = (self.transform(*symmetry), *symmetry);
+            }
+        }
+        return best;
+    }
+
+    /// Returns whether the game goes on, the side to move has to pass, or
+    /// the game is over and who won.
+    pub fn get_game_status(&self) -> GameStatus {
+        let move_generator = MoveGenerator::new();
+        if move_generator.generate_legal_moves(self) != 0 {
+            return GameStatus::Ongoing;
+        }
+        if move_generator.must_pass(self) {
+            return GameStatus::MustPass;
+        }
+
+        let mut score = [self.get_num_pieces(WHITE), self.get_num_pieces(BLACK)];
+        let empty_squares = 64 - score[0] - score[1];
+        let winner = if score[0] > score[1] {
+            Some(0)
+        }
+        else if score[1] > score[0] {
+            Some(1)
+        }
+        else {
+            None
+        };
+        if let Some(side) = winner {
+            score[side] += empty_squares;
+        }
+        return GameStatus::Finished { winner, score };
+    }
+
+    /// Returns the board as an 8x8 grid, one row per line, `W` for white,
+    /// `B` for black discs and `.` for empty squares.
+    pub fn to_grid_string(&self) -> String {
+        let mut grid = String::new();
+        for square in 0..64 {
+            let bit = 1 << square;
+            if self.occ_squares[WHITE] & bit != 0 {
+                grid.push_str("W ");
+            }
+            else if self.occ_squares[BLACK] & bit != 0 {
+                grid.push_str("B ");
+            }
+            else {
+                grid.push_str(". ");
+            }
+            if square % 8 == 7 {
+                grid.push('\n');
+            }
+        }
+        return grid;
+    }
+
+    pub(crate) fn shift_bitboard_in_dir(&self, bitboard: u64, direction: usize) -> u64 {
+        match direction {
+            0 => bitboard << 8,
+            1 => bitboard << 1,
+            2 => bitboard << 9,
+            3 => bitboard << 7,
+            4 => bitboard >> 8,
+            5 => bitboard >> 1,
+            6 => bitboard >> 7,
+            7 => bitboard >> 9,
+            _ => bitboard
+        }
+    }
+
+    /// Returns the side whose turn it is.
+    pub fn get_side_to_move(&self) -> usize {
+        return self.side_to_move;
+    }
+
+    /// Hands the turn to the other side without placing a disc.
+    pub fn switch_side_to_move(&mut self) {
+        self.side_to_move ^= 1;
+        self.hash ^= zobrist::SIDE_KEY;
+    }
+
+    /// Returns the Zobrist hash of the position, including the side to
+    /// move. It is kept up to date by `make_move` and `unmake_move`.
+    pub fn get_hash(&self) -> u64 {
+        return self.hash;
+    }
+
+    /// Returns the bitboard of discs belonging to `side`.
+    pub fn get_side_squares(&self, side: usize) -> u64 {
+        return self.occ_squares[side];
+    }
+
+    /// Returns the number of discs belonging to `side`.
+    pub fn get_num_pieces(&self, side: usize) -> i32 {
+        return self.occ_squares[side].count_ones() as i32;
+    }
+
+    /// Returns the bitboard of all occupied squares.
+    pub fn get_occ_squares(&self) -> u64 {
+        return self.occ_squares[0] | self.occ_squares[1];
+    }
+
+    /// Returns the bitboard of all empty squares.
+    pub fn get_empty_squares(&self) -> u64 {
+        return !self.get_occ_squares();
+    }
+
+    /// Returns the discs of `side` that can never be flipped for the rest
+    /// of the game. A disc is stable when, along each of the four lines
+    /// through it, the line is full or a neighbour on the line is the
+    /// board edge or another stable disc of the same side. This finds the
+    /// corners, filled edges and lines and the regions anchored on them,
+    /// though not every stable disc.
+    ///
+    /// ```
+    /// use othello::{Board, BLACK, WHITE};
+    ///
+    /// // Nothing is stable at the start.
+    /// let board = Board::std_start_pos(BLACK);
+    /// assert_eq!(board.get_stable_discs(BLACK) | board.get_stable_discs(WHITE), 0);
+    ///
+    /// // Black's a1 corner region is stable, white's d1 can still be flipped.
+    /// let board: Board = "XXXO----X------------------------------------------------------- O".parse().unwrap();
+    /// assert_eq!(board.get_stable_discs(BLACK), 0b1_0000_0111);
+    /// assert_eq!(board.get_stable_discs(WHITE), 0);
+    ///
+    /// // Every disc of a filled edge is stable.
+    /// let board: Board = "XOXOOXXO-------------------------------------------------------- X".parse().unwrap();
+    /// assert_eq!(board.get_stable_discs(BLACK), board.get_side_squares(BLACK) & 0xFF);
+    /// assert_eq!(board.get_stable_discs(WHITE), board.get_side_squares(WHITE) & 0xFF);
+    /// ```
+    pub fn get_stable_discs(&self, side: usize) -> u64 {
+        let own = self.occ_squares[side];
+        let occ = self.get_occ_squares();
+        let mut full = [0u64; 4];
+        for row in 0..8 {
+            let mask = 0xFFu64 << (8 * row);
+            if occ & mask == mask {
+                full[0] |= mask;
+            }
+            let mask = A_FILE << row;
+            if occ & mask == mask {
+                full[1] |= mask;
+            }
+        }
+        for (line, mask) in DIAGONALS.iter().chain(ANTI_DIAGONALS.iter()).enumerate() {
+            if occ & mask == *mask {
+                full[if line < 15 { 2 } else { 3 }] |= mask;
+            }
+        }
+        // Squares already safe along each line: full lines and the edges.
+        let safe = [
+            full[0] | A_FILE | H_FILE,
+            full[1] | RANK_1 | RANK_8,
+            full[2] | EDGES,
+            full[3] | EDGES,
+        ];
+
+        let mut stable = 0;
+        loop {
+            let horizontal = ((stable << 1) & !A_FILE) | ((stable >> 1) & !H_FILE);
+            let vertical = (stable << 8) | (stable >> 8);
+            let diagonal = ((stable << 9) & !A_FILE) | ((stable >> 9) & !H_FILE);
+            let anti_diagonal = ((stable << 7) & !H_FILE) | ((stable >> 7) & !A_FILE);
+            let new_stable = stable
+                | (own
+                    & (safe[0] | horizontal)
+                    & (safe[1] | vertical)
+                    & (safe[2] | diagonal)
+                    & (safe[3] | anti_diagonal));
+            if new_stable == stable {
+                return stable;
+            }
+            stable = new_stable;
+        }
+    }
+}
+
+impl PartialEq for Board {
+    fn eq(&self, other: &Board) -> bool {
+        return self.occ_squares == other.occ_squares && self.side_to_move == other.side_to_move;
+    }
+}
+
+impl Eq for Board {}
+
+impl Hash for Board {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+/// Formats the board as 64 squares row by row from a1 to h8, `X` for
+/// black, `O` for white and `-` for empty, followed by a space and the
+/// side to move.
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for square in 0..64 {
+            let bit = 1 << square;
+            if self.occ_squares[BLACK] & bit != 0 {
+                write!(f, "X")?;
+            }
+            else if self.occ_squares[WHITE] & bit != 0 {
+                write!(f, "O")?;
+            }
+            else {
+                write!(f, "-")?;
+            }
+        }
+        let side = if self.side_to_move == BLACK { "X" } else { "O" };
+        return write!(f, " {}", side);
+    }
+}
+
+/// Parses the format written by `Display`. Squares are case-insensitive,
+/// `*` is accepted for black and `.` for empty.
+///
+/// ```
+/// use othello::{Board, BLACK};
+///
+/// let pos = "---------------------------OX------XO--------------------------- X";
+/// let board: Board = pos.parse().unwrap();
+/// assert_eq!(board.get_side_to_move(), BLACK);
+/// assert_eq!(board.to_string(), pos);
+/// assert_eq!(board.to_string(), Board::std_start_pos(BLACK).to_string());
+/// ```
+impl FromStr for Board {
+    type Err = ParseBoardError;
+
+    fn from_str(s: &str) -> Result<Board, ParseBoardError> {
+        let mut parts = s.split_whitespace();
+        let squares = parts.next().unwrap_or("");
+        let len = squares.chars().count();
+        if len != 64 {
+            return Err(ParseBoardError::WrongLength(len));
+        }
+
+        let mut white: u64 = 0;
+        let mut black: u64 = 0;
+        for (index, found) in squares.chars().enumerate() {
+            match found {
+                'X' | 'x' | '*' => black |= 1 << index,
+                'O' | 'o' => white |= 1 << index,
+                '-' | '.' => {}
+                _ => return Err(ParseBoardError::InvalidSquare { index, found }),
+            }
+        }
+
+        let side_to_move = match parts.next() {
+            Some("X") | Some("x") | Some("*") => BLACK,
+            Some("O") | Some("o") => WHITE,
+            Some(side) => return Err(ParseBoardError::InvalidSideToMove(side.to_string())),
+            None => return Err(ParseBoardError::MissingSideToMove),
+        };
+        if let Some(extra) = parts.next() {
+            return Err(ParseBoardError::TrailingInput(extra.to_string()));
+        }
+        return Ok(Board::new(white, black, side_to_move));
+    }
+}
+
+pub(crate) const A_FILE: u64 = 0x0101010101010101;
+pub(crate) const H_FILE: u64 = 0x8080808080808080;
+const RANK_1: u64 = 0xFF;
+const RANK_8: u64 = 0xFF00000000000000;
+const EDGES: u64 = A_FILE | H_FILE | RANK_1 | RANK_8;
+
+// The 15 lines parallel to a1-h8 and the 15 parallel to h1-a8.
+const DIAGONALS: [u64; 15] = diagonal_masks(9);
+const ANTI_DIAGONALS: [u64; 15] = diagonal_masks(7);
+
+const fn diagonal_masks(step: u32) -> [u64; 15] {
+    let mut masks = [0u64; 15];
+    let mut square = 0;
+    while square < 64 {
+        let (row, col) = (square / 8, square % 8);
+        let line = if step == 9 { col + 7 - row } else { col + row };
+        masks[line] |= 1 << square;
+        square += 1;
+    }
+    return masks;
+}
+
+/// Returns the mask applied to the discs a shift in `direction` may pass
+/// over, see `shift_bitboard_in_dir`.
+pub(crate) fn get_dir_mask(direction: usize) -> u64 {
+    match direction {
+        0 | 4 => u64::MAX,
+        _ => BoardPositions::BitBoardInnerFiles as u64,
+    }
+}
+
+/// Returns a bitboard as an 8x8 grid of ones and zeros, one row per line.
+pub fn bitboard_to_string(bitboard: u64) -> String {
+    let mut grid = String::new();
+    for square in 0..64 {
+        grid.push_str(if bitboard & (1 << square) != 0 { "1 " } else { "0 " });
+        if square % 8 == 7 {
+            grid.push('\n');
+        }
+    }
+    return grid;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use rand::rngs::StdRng;
+    use rand::seq::IteratorRandom;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    // Plays a random game from the start position and returns every
+    // position reached together with the move played from it.
+    fn random_game(rng: &mut StdRng) -> Vec<(Board, Move)> {
+        let move_generator = MoveGenerator::new();
+        let mut board = Board::std_start_pos(BLACK);
+        let mut history = vec![];
+        while let Some(mv) = move_generator.legal_move_iter(&board).choose(rng) {
+            history.push((board, mv));
+            board.make_move(mv);
+        }
+        return history;
+    }
+
+    #[test]
+    fn unmake_move_restores_position() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut passes = 0;
+        for _ in 0..200 {
+            for (before, mv) in random_game(&mut rng) {
+                let mut board = before;
+                let undo = board.make_move(mv);
+                assert_ne!(board, before);
+                board.unmake_move(undo);
+                assert_eq!(board, before);
+                assert_eq!(board.get_hash(), before.get_hash());
+                if mv.is_pass() {
+                    passes += 1;
+                }
+            }
+        }
+        assert!(passes > 0);
+    }
+
+    #[test]
+    fn unmake_move_unwinds_whole_game() {
+        let mut rng = StdRng::seed_from_u64(4);
+        for _ in 0..50 {
+            let history = random_game(&mut rng);
+            let mut board = Board::std_start_pos(BLACK);
+            let undos: Vec<UndoInfo> = history.iter().map(|&(_, mv)| board.make_move(mv)).collect();
+            for (&(before, _), &undo) in history.iter().zip(undos.iter()).rev() {
+                board.unmake_move(undo);
+                assert_eq!(board, before);
+                assert_eq!(board.get_hash(), before.get_hash());
+            }
+        }
+    }
+
+    #[test]
+    fn parse_round_trips_random_positions() {
+        let mut rng = StdRng::seed_from_u64(6);
+        for _ in 0..20 {
+            for (board, _) in random_game(&mut rng) {
+                for side in [WHITE, BLACK] {
+                    let board = Board::new(board.get_side_squares(WHITE), board.get_side_squares(BLACK), side);
+                    let parsed: Board = board.to_string().parse().unwrap();
+                    assert_eq!(parsed, board);
+                    assert_eq!(parsed.get_hash(), board.get_hash());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parse_errors_describe_the_problem() {
+        let start = "---------------------------OX------XO---------------------------";
+        let cases = [
+            ("-".repeat(63) + " X", ParseBoardError::WrongLength(63), "expected 64 squares, found 63"),
+            (
+                format!("{}?{} X", &start[..10], &start[11..]),
+                ParseBoardError::InvalidSquare { index: 10, found: '?' },
+                "invalid character '?' on square 10, expected 'X', 'O' or '-'",
+            ),
+            (start.to_string(), ParseBoardError::MissingSideToMove, "missing side to move after the board"),
+            (
+                format!("{} B", start),
+                ParseBoardError::InvalidSideToMove("B".to_string()),
+                "invalid side to move 'B', expected 'X' or 'O'",
+            ),
+            (
+                format!("{} X 12", start),
+                ParseBoardError::TrailingInput("12".to_string()),
+                "unexpected '12' after the side to move",
+            ),
+        ];
+        for (text, error, message) in cases {
+            assert_eq!(text.parse::<Board>().unwrap_err(), error);
+            assert_eq!(error.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn symmetric_positions_share_canonical_board() {
+        let mut rng = StdRng::seed_from_u64(11);
+        for _ in 0..20 {
+            for (board, _) in random_game(&mut rng) {
+                let (canonical, symmetry) = board.canonical();
+                assert_eq!(board.transform(symmetry), canonical);
+                for variant in Symmetry::ALL {
+                    assert_eq!(board.transform(variant).canonical().0, canonical);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn canonical_moves_map_back_to_legal_moves() {
+        let move_generator = MoveGenerator::new();
+        let mut rng = StdRng::seed_from_u64(12);
+        for _ in 0..20 {
+            for (board, _) in random_game(&mut rng) {
+                let (canonical, symmetry) = board.canonical();
+                let mut mapped = vec![];
+                for mv in move_generator.legal_move_iter(&canonical) {
+                    let mv = mv.transform(symmetry.inverse());
+                    let mut child = board;
+                    assert!(child.try_make_move(mv).is_ok());
+                    mapped.push(mv);
+                }
+                let mut moves = move_generator.generate_legal_move_list(&board);
+                mapped.sort_by_key(|mv| mv.get_square());
+                moves.sort_by_key(|mv| mv.get_square());
+                assert_eq!(mapped, moves);
+            }
+        }
+    }
+
+    #[test]
+    fn incremental_hash_matches_full_hash() {
+        let mut rng = StdRng::seed_from_u64(12);
+        for _ in 0..200 {
+            let history = random_game(&mut rng);
+            let mut board = Board::std_start_pos(BLACK);
+            for (_, mv) in history {
+                board.make_move(mv);
+                let occ_squares = [board.get_side_squares(WHITE), board.get_side_squares(BLACK)];
+                assert_eq!(board.get_hash(), zobrist::hash_position(occ_squares, board.get_side_to_move()));
+            }
+        }
+    }
+
+    #[test]
+    fn hash_includes_side_to_move() {
+        let mut rng = StdRng::seed_from_u64(13);
+        for (board, _) in random_game(&mut rng) {
+            let mut passed = board;
+            passed.switch_side_to_move();
+            assert_ne!(passed.get_hash(), board.get_hash());
+            assert_ne!(passed, board);
+        }
+    }
+
+    #[test]
+    fn distinct_positions_have_distinct_hashes() {
+        let mut rng = StdRng::seed_from_u64(14);
+        let mut positions = HashMap::new();
+        while positions.len() < 20_000 {
+            for (board, _) in random_game(&mut rng) {
+                if let Some(other) = positions.insert(board.get_hash(), board) {
+                    assert_eq!(other, board, "hash collision");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn boards_work_as_hash_set_keys() {
+        let mut rng = StdRng::seed_from_u64(15);
+        let mut set = HashSet::new();
+        let mut count = 0;
+        for _ in 0..50 {
+            for (board, _) in random_game(&mut rng) {
+                if set.insert(board) {
+                    count += 1;
+                }
+                // Boards reached along a different path are the same key.
+                let copy = Board::new(board.get_side_squares(WHITE), board.get_side_squares(BLACK), board.get_side_to_move());
+                assert!(set.contains(&copy));
+                assert!(!set.insert(copy));
+                let mut passed = board;
+                passed.switch_side_to_move();
+                assert_eq!(set.contains(&passed), set.iter().any(|other| *other == passed));
+            }
+        }
+        assert_eq!(set.len(), count);
+    }
+
+    // Flips found by walking from `square` one square at a time.
+    fn reference_flips(board: &Board, square: u32, side: usize) -> u64 {
+        let mut flips = 0;
+        for (d_row, d_col) in [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)] {
+            let (mut row, mut col) = ((square / 8) as i32 + d_row, (square % 8) as i32 + d_col);
+            let mut line = 0;
+            while (0..8).contains(&row) && (0..8).contains(&col) {
+                let bit = 1 << (row * 8 + col);
+                if board.get_side_squares(side ^ 1) & bit != 0 {
+                    line |= bit;
+                }
+                else {
+                    if board.get_side_squares(side) & bit != 0 {
+                        flips |= line;
+                    }
+                    break;
+                }
+                row += d_row;
+                col += d_col;
+            }
+        }
+        return flips;
+    }
+
+    #[test]
+    fn moves_do_not_wrap_around_edges() {
+        // Black h1 and white a2 are neighbours in bit order only. Shifts
+        // that wrapped between rows made b2 legal for black and g1 for
+        // white.
+        let board: Board = "-------XO------------------------------------------------------- X".parse().unwrap();
+        let move_generator = MoveGenerator::new();
+        assert_eq!(move_generator.generate_legal_moves(&board), 0);
+        assert_eq!(board.get_flips(9, BLACK), 0);
+        let mut passed = board;
+        passed.switch_side_to_move();
+        assert_eq!(move_generator.generate_legal_moves(&passed), 0);
+        assert_eq!(board.get_flips(6, WHITE), 0);
+        assert!(matches!(board.get_game_status(), GameStatus::Finished { .. }));
+    }
+
+    #[test]
+    fn flips_match_reference_on_random_positions() {
+        let move_generator = MoveGenerator::new();
+        let mut rng = StdRng::seed_from_u64(13);
+        for _ in 0..2000 {
+            // Five in eight squares occupied, split evenly between sides.
+            let occupied = rng.gen::<u64>() | (rng.gen::<u64>() & rng.gen::<u64>());
+            let black = occupied & rng.gen::<u64>();
+            for side in [WHITE, BLACK] {
+                let board = Board::new(occupied & !black, black, side);
+                let mut legal = 0;
+                for square in 0..64 {
+                    if occupied & (1 << square) != 0 {
+                        continue;
+                    }
+                    let flips = reference_flips(&board, square, side);
+                    assert_eq!(board.get_flips(square, side), flips, "{} {}", board, square);
+                    if flips != 0 {
+                        legal |= 1 << square;
+                    }
+                }
+                assert_eq!(move_generator.generate_legal_moves(&board), legal, "{}", board);
+            }
+        }
+    }
+
+    #[test]
+    fn rejected_moves_leave_board_untouched() {
+        let start = Board::std_start_pos(BLACK);
+        let cases = [
+            (start, Move::new(64, BLACK), MoveError::OutOfRange),
+            (start, Move::new(37, WHITE), MoveError::WrongSide),
+            (start, Move::new(27, BLACK), MoveError::Occupied),
+            (start, Move::new(0, BLACK), MoveError::NoFlips),
+            (start, Move::pass(BLACK), MoveError::IllegalPass),
+        ];
+        for (before, mv, error) in cases {
+            let mut board = before;
+            assert_eq!(board.try_make_move(mv), Err(error), "{}", mv);
+            assert_eq!(board, before);
+            assert_eq!(board.get_hash(), before.get_hash());
+        }
+
+        let mut board = start;
+        let undo = board.try_make_move(Move::new(37, BLACK)).unwrap();
+        assert_eq!(undo.flipped, 1 << 36);
+        assert_eq!(board.get_num_pieces(BLACK), 4);
+    }
+
+    #[test]
+    fn game_status_covers_every_state() {
+        assert_eq!(Board::std_start_pos(BLACK).get_game_status(), GameStatus::Ongoing);
+
+        // White has no capture but black does.
+        let board: Board = "XO-----------------------------------------------------------OOX O".parse().unwrap();
+        assert_eq!(board.get_game_status(), GameStatus::MustPass);
+
+        // Neither side can capture, with one disc each.
+        let board: Board = "X--------------------------------------------------------------O X".parse().unwrap();
+        assert_eq!(board.get_game_status(), GameStatus::Finished { winner: None, score: [1, 1] });
+
+        // Black wins 2-1 and gets the 61 empty squares.
+        let board: Board = "XX-------------------------------------------------------------O O".parse().unwrap();
+        assert_eq!(board.get_game_status(), GameStatus::Finished { winner: Some(BLACK), score: [1, 63] });
+
+        // A full board has nothing to hand out.
+        let board: Board = format!("{}{} X", "O".repeat(40), "X".repeat(24)).parse().unwrap();
+        assert_eq!(board.get_game_status(), GameStatus::Finished { winner: Some(WHITE), score: [40, 24] });
+    }
+
+    #[test]
+    fn full_lines_make_interior_discs_stable() {
+        // A black disc on d3 with its row, column and both diagonals
+        // filled by white discs.
+        let d3 = 19;
+        let lines = [0xFF << 16, A_FILE << 3, DIAGONALS[3 + 7 - 2], ANTI_DIAGONALS[3 + 2]];
+        let full = lines.iter().fold(0, |full, line| full | line);
+        let board = Board::new(full & !(1 << d3), 1 << d3, BLACK);
+        assert_eq!(board.get_stable_discs(BLACK), 1 << d3);
+
+        // Emptying an end of any one line lets d3 be flipped along it.
+        for empty in [16, 59, 55, 40] {
+            assert_eq!(lines.iter().filter(|&&line| line & (1 << empty) != 0).count(), 1);
+            let board = Board::new(full & !(1 << d3) & !(1 << empty), 1 << d3, BLACK);
+            assert_eq!(board.get_stable_discs(BLACK), 0, "{}", empty);
+        }
+    }
+
+    // Checks that no continuation of `board` flips a disc of `stable`.
+    fn assert_stable_in_subtree(board: &Board, stable: [u64; 2], move_generator: &MoveGenerator) {
+        for side in [WHITE, BLACK] {
+            assert_eq!(board.get_side_squares(side) & stable[side], stable[side], "{}", board);
+        }
+        for mv in move_generator.legal_move_iter(board) {
+            let mut child = *board;
+            child.make_move(mv);
+            assert_stable_in_subtree(&child, stable, move_generator);
+        }
+    }
+
+    #[test]
+    fn stable_discs_are_never_flipped() {
+        let move_generator = MoveGenerator::new();
+        let mut rng = StdRng::seed_from_u64(24);
+        let mut n_stable = 0;
+        // Positions with 9 or 10 empties have large trees, so only one in
+        // 25 gets that many.
+        for i in 0..300 {
+            let empties = if i % 25 == 0 { rng.gen_range(9..=10) } else { rng.gen_range(4..=8) };
+            let board = match random_game(&mut rng).into_iter().find(|(board, _)| board.get_empty_squares().count_ones() <= empties) {
+                Some((board, _)) => board,
+                None => continue,
+            };
+            let stable = [board.get_stable_discs(WHITE), board.get_stable_discs(BLACK)];
+            n_stable += (stable[0] | stable[1]).count_ones();
+            assert_stable_in_subtree(&board, stable, &move_generator);
+        }
+        assert!(n_stable > 1000);
+    }
+}
@@ -0,0 +1,218 @@
+//! Classical evaluation terms and a tunable evaluator combining them.
+//!
+//! Each term counts something for both sides and scores the difference
+//! from the side to move's view: discs, legal moves, potential mobility
+//! (empty squares next to opponent discs), frontier discs (discs next to
+//! empty squares), corners, X- and C-squares next to empty corners and
+//! stable discs.
+
+use std::fmt;
+
+use crate::board::{Board, A_FILE, H_FILE};
+use crate::evaluator::{Evaluator, EVAL_SCALE};
+use crate::move_generator::MoveGenerator;
+
+/// Number of terms of the classical evaluation.
+pub const N_TERMS: usize = 8;
+
+const CORNERS: u64 = 0x8100000000000081;
+
+// Corner, X-square and the two C-squares of each corner.
+const CORNER_REGIONS: [(u32, u32, [u32; 2]); 4] = [
+    (0, 9, [1, 8]),
+    (7, 14, [6, 15]),
+    (56, 49, [48, 57]),
+    (63, 54, [55, 62]),
+];
+
+/// A term of the classical evaluation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Term {
+    Discs,
+    Mobility,
+    PotentialMobility,
+    Frontier,
+    Corners,
+    XSquares,
+    CSquares,
+    Stability,
+}
+
+impl Term {
+    /// All terms in the order used by weight and value arrays.
+    pub const ALL: [Term; N_TERMS] = [
+        Term::Discs,
+        Term::Mobility,
+        Term::PotentialMobility,
+        Term::Frontier,
+        Term::Corners,
+        Term::XSquares,
+        Term::CSquares,
+        Term::Stability,
+    ];
+
+    /// Returns the short name of the term, e.g. `mobility`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Term::Discs => "discs",
+            Term::Mobility => "mobility",
+            Term::PotentialMobility => "potential_mobility",
+            Term::Frontier => "frontier",
+            Term::Corners => "corners",
+            Term::XSquares => "x_squares",
+            Term::CSquares => "c_squares",
+            Term::Stability => "stability",
+        }
+    }
+
+    /// Returns the count of the term for `side`.
+    pub fn count(&self, board: &Board, side: usize) -> i32 {
+        let count = match self {
+            Term::Discs => board.get_side_squares(side).count_ones(),
+            Term::Mobility => mobility(board, side),
+            Term::PotentialMobility => potential_mobility(board, side),
+            Term::Frontier => frontier_discs(board, side).count_ones(),
+            Term::Corners => corner_discs(board, side).count_ones(),
+            Term::XSquares => x_square_discs(board, side).count_ones(),
+            Term::CSquares => c_square_discs(board, side).count_ones(),
+            Term::Stability => board.get_stable_discs(side).count_ones(),
+        };
+        return count as i32;
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Returns the number of legal moves `side` would have in `board`.
+pub fn mobility(board: &Board, side: usize) -> u32 {
+    let mut board = *board;
+    if board.get_side_to_move() != side {
+        board.switch_side_to_move();
+    }
+    return MoveGenerator::new().generate_legal_moves(&board).count_ones();
+}
+
+/// Returns the number of empty squares next to discs of `side`'s opponent,
+/// the squares `side` may be able to play later.
+pub fn potential_mobility(board: &Board, side: usize) -> u32 {
+    return (neighbours(board.get_side_squares(side ^ 1)) & board.get_empty_squares()).count_ones();
+}
+
+/// Returns the discs of `side` next to an empty square.
+pub fn frontier_discs(board: &Board, side: usize) -> u64 {
+    return board.get_side_squares(side) & neighbours(board.get_empty_squares());
+}
+
+/// Returns the corners held by `side`.
+pub fn corner_discs(board: &Board, side: usize) -> u64 {
+    return board.get_side_squares(side) & CORNERS;
+}
+
+/// Returns the X-squares held by `side` whose corner is still empty.
+pub fn x_square_discs(board: &Board, side: usize) -> u64 {
+    let empty = board.get_empty_squares();
+    let mut squares = 0;
+    for (corner, x_square, _) in CORNER_REGIONS {
+        if empty & (1 << corner) != 0 {
+            squares |= 1 << x_square;
+        }
+    }
+    return board.get_side_squares(side) & squares;
+}
+
+/// Returns the C-squares held by `side` whose corner is still empty.
+pub fn c_square_discs(board: &Board, side: usize) -> u64 {
+    let empty = board.get_empty_squares();
+    let mut squares = 0;
+    for (corner, _, c_squares) in CORNER_REGIONS {
+        if empty & (1 << corner) != 0 {
+            squares |= (1 << c_squares[0]) | (1 << c_squares[1]);
+        }
+    }
+    return board.get_side_squares(side) & squares;
+}
+
+// Squares next to a square of `bitboard`, excluding `bitboard` itself.
+fn neighbours(bitboard: u64) -> u64 {
+    let row = bitboard | ((bitboard << 1) & !A_FILE) | ((bitboard >> 1) & !H_FILE);
+    return (row | (row << 8) | (row >> 8)) & !bitboard;
+}
+
+/// One term's share of a classical evaluation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TermScore {
+    pub term: Term,
+    /// Count for the side to move minus the opponent's count.
+    pub value: i32,
+    /// `value` times the term's weight, in `EVAL_SCALE` units.
+    pub score: i32,
+}
+
+/// Scores positions by a weighted sum of the classical terms. Weights are
+/// in `EVAL_SCALE` units per unit of difference between the sides.
+///
+/// ```
+/// use othello::{Board, ClassicalEvaluator, Evaluator, Term, BLACK};
+///
+/// let evaluator = ClassicalEvaluator::default();
+/// let board = Board::std_start_pos(BLACK);
+/// assert_eq!(evaluator.evaluate(&board), 0);
+/// let breakdown = evaluator.breakdown(&board);
+/// assert_eq!(breakdown[Term::Mobility as usize].value, 0);
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct ClassicalEvaluator {
+    weights: [i32; N_TERMS],
+}
+
+impl ClassicalEvaluator {
+    /// Creates an evaluator with the weights of `Term::ALL` in order.
+    pub fn new(weights: [i32; N_TERMS]) -> ClassicalEvaluator {
+        return ClassicalEvaluator { weights };
+    }
+
+    /// Returns the weight of `term`.
+    pub fn get_weight(&self, term: Term) -> i32 {
+        return self.weights[term as usize];
+    }
+
+    /// Sets the weight of `term`, e.g. to tune one term at a time.
+    pub fn set_weight(&mut self, term: Term, weight: i32) {
+        self.weights[term as usize] = weight;
+    }
+
+    /// Returns every term's value and score for `board`, in the order of
+    /// `Term::ALL`. The scores add up to `evaluate`.
+    pub fn breakdown(&self, board: &Board) -> [TermScore; N_TERMS] {
+        let side = board.get_side_to_move();
+        return Term::ALL.map(|term| {
+            let value = term.count(board, side) - term.count(board, side ^ 1);
+            return TermScore { term, value, score: value * self.weights[term as usize] };
+        });
+    }
+}
+
+impl Default for ClassicalEvaluator {
+    fn default() -> ClassicalEvaluator {
+        return ClassicalEvaluator::new([
+            EVAL_SCALE / 8,
+            EVAL_SCALE,
+            EVAL_SCALE / 2,
+            -EVAL_SCALE / 2,
+            8 * EVAL_SCALE,
+            -4 * EVAL_SCALE,
+            -EVAL_SCALE,
+            EVAL_SCALE,
+        ]);
+    }
+}
+
+impl Evaluator for ClassicalEvaluator {
+    fn evaluate(&self, board: &Board) -> i32 {
+        return self.breakdown(board).iter().map(|term| term.score).sum();
+    }
+}
@@ -0,0 +1,456 @@
+//! Exact endgame solving.
+//!
+//! With few empty squares left the whole remaining game tree can be
+//! searched, giving the exact final disc differential instead of a
+//! heuristic estimate. Scores follow `GameStatus::Finished`: empty squares
+//! left at the end go to the winner, so they range from -64 to 64 from the
+//! side to move's view.
+
+use std::fmt;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::board::Board;
+use crate::move_generator::{MoveGenerator, MoveIter};
+use crate::moves::Move;
+use crate::stop::StopCondition;
+
+/// Default number of empty squares from which `Search` solves exactly.
+pub const DEFAULT_ENDGAME_EMPTIES: u32 = 14;
+
+/// Default number of empty squares from which `Search` solves for the
+/// game-theoretic outcome only.
+pub const DEFAULT_WLD_EMPTIES: u32 = 18;
+
+/// Highest possible final disc differential.
+pub const MAX_SCORE: i32 = 64;
+
+// Empties from which moves are ordered by opponent mobility (fastest
+// first). Closer to the end parity ordering alone is cheaper.
+const FASTEST_FIRST_MIN_EMPTIES: u32 = 7;
+
+// Empties from which the opponent's stable discs are counted to prune
+// positions that cannot reach alpha.
+const STABILITY_CUTOFF_MIN_EMPTIES: u32 = 6;
+
+// The four 4x4 quadrants of the board, used for parity ordering.
+const QUADRANTS: [u64; 4] = [
+    0x0000_0000_0F0F_0F0F,
+    0x0000_0000_F0F0_F0F0,
+    0x0F0F_0F0F_0000_0000,
+    0xF0F0_F0F0_0000_0000,
+];
+
+/// Game-theoretic result of a position for the side to move.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Win,
+    Draw,
+    Loss,
+}
+
+impl Outcome {
+    /// Returns the outcome of a final disc differential.
+    pub fn from_score(score: i32) -> Outcome {
+        if score > 0 {
+            return Outcome::Win;
+        }
+        if score < 0 {
+            return Outcome::Loss;
+        }
+        return Outcome::Draw;
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Win => write!(f, "win"),
+            Outcome::Draw => write!(f, "draw"),
+            Outcome::Loss => write!(f, "loss"),
+        }
+    }
+}
+
+/// Searches positions to the end of the game for their exact score.
+pub struct EndgameSolver {
+    move_generator: MoveGenerator,
+    nodes: u64,
+    stop: StopCondition,
+    stopped: bool,
+}
+
+impl EndgameSolver {
+    /// Creates a solver without limits.
+    pub fn new(move_generator: MoveGenerator) -> EndgameSolver {
+        return EndgameSolver {
+            move_generator,
+            nodes: 0,
+            stop: StopCondition::default(),
+            stopped: false,
+        };
+    }
+
+    /// Sets a flag that aborts the solve when another thread sets it.
+    pub fn set_stop_flag(&mut self, stop_flag: Option<Arc<AtomicBool>>) {
+        self.stop.set_stop_flag(stop_flag);
+    }
+
+    /// Aborts solves still running at `deadline`.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.stop.set_deadline(deadline);
+    }
+
+    /// Aborts solves after visiting `max_nodes` nodes.
+    pub fn set_max_nodes(&mut self, max_nodes: Option<u64>) {
+        self.stop.set_max_nodes(max_nodes);
+    }
+
+    /// Returns the number of positions visited by the last solve.
+    pub fn get_nodes(&self) -> u64 {
+        return self.nodes;
+    }
+
+    /// Returns true if the last solve hit a limit before finishing, in
+    /// which case its result is meaningless.
+    pub fn is_stopped(&self) -> bool {
+        return self.stopped;
+    }
+
+    /// Returns the best move and the exact final disc differential from
+    /// the side to move's view. The move is a pass if the side to move has
+    /// no placement and `None` if the game is over.
+    ///
+    /// ```
+    /// use othello::{Board, EndgameSolver, MoveGenerator};
+    ///
+    /// // Black to move fills the last square, flipping six discs.
+    /// let board: Board = "XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXOOOOOOOOOOOOOOOOOOOOOOOOOOOOOO-X X".parse().unwrap();
+    /// let mut solver = EndgameSolver::new(MoveGenerator::new());
+    /// let (mv, score) = solver.solve(&board);
+    /// assert_eq!(mv.unwrap().to_string(), "g8");
+    /// assert_eq!(score, 16);
+    /// ```
+    pub fn solve(&mut self, board: &Board) -> (Option<Move>, i32) {
+        return self.solve_window(board, -MAX_SCORE, MAX_SCORE);
+    }
+
+    /// Determines only whether the side to move wins, draws or loses, with
+    /// two null-window solves. The move proves the outcome: it wins or
+    /// draws whatever the opponent replies. For a loss it is just a legal
+    /// move. Returns `None` if a limit stopped the solve.
+    pub fn solve_wld(&mut self, board: &Board) -> Option<(Option<Move>, Outcome)> {
+        let (best_move, score) = self.solve_window(board, 0, 1);
+        if self.stopped {
+            return None;
+        }
+        if score > 0 {
+            return Some((best_move, Outcome::Win));
+        }
+        let nodes = self.nodes;
+        let (best_move, score) = self.solve_window(board, -1, 0);
+        self.nodes += nodes;
+        if self.stopped {
+            return None;
+        }
+        return Some((best_move, Outcome::from_score(score.min(0))));
+    }
+
+    /// Like `solve`, but only within the window `alpha..beta`: a score at
+    /// or below `alpha` is an upper bound and one at or above `beta` a
+    /// lower bound. Narrow windows solve much faster.
+    pub fn solve_window(&mut self, board: &Board, mut alpha: i32, beta: i32) -> (Option<Move>, i32) {
+        self.nodes = 0;
+        self.stopped = false;
+        let empties = board.get_empty_squares().count_ones();
+        let side = board.get_side_to_move();
+
+        let moves = self.move_generator.generate_legal_moves(board);
+        if moves == 0 {
+            let mut passed = *board;
+            passed.switch_side_to_move();
+            if self.move_generator.generate_legal_moves(&passed) == 0 {
+                return (None, final_score(board));
+            }
+            let score = -self.solve_node(&passed, -beta, -alpha, empties, true);
+            return (Some(Move::pass(side)), score);
+        }
+
+        let mut best = (None, -MAX_SCORE - 1);
+        for mv in self.order_moves(board, moves, empties) {
+            let mut child = *board;
+            child.make_move(mv);
+            let score = -self.solve_node(&child, -beta, -alpha, empties - 1, false);
+            if self.stopped {
+                break;
+            }
+            if score > best.1 {
+                best = (Some(mv), score);
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        return best;
+    }
+
+    // Replaces every limit at once, as `Search` does before each solve.
+    pub(crate) fn set_stop_condition(&mut self, stop: StopCondition) {
+        self.stop = stop;
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if !self.stopped && self.stop.check(self.nodes) {
+            self.stopped = true;
+        }
+        return self.stopped;
+    }
+
+    // Moves of the side to move, fastest first with many empties and by
+    // quadrant parity otherwise.
+    fn order_moves(&self, board: &Board, moves: u64, empties: u32) -> impl Iterator<Item = Move> {
+        let side = board.get_side_to_move();
+        let parity = parity_mask(board.get_empty_squares());
+        let fastest_first = empties >= FASTEST_FIRST_MIN_EMPTIES;
+        let move_generator = self.move_generator;
+        return MoveIter::new(moves, side).ordered(move |mv| {
+            let square = mv.get_square().unwrap();
+            let mut score = if parity & (1 << square) != 0 { 1 } else { 0 };
+            if fastest_first {
+                let mut child = *board;
+                child.make_move(mv);
+                score -= move_generator.generate_legal_moves(&child).count_ones() as i32 * 4;
+            }
+            return score;
+        });
+    }
+
+    fn solve_node(&mut self, board: &Board, mut alpha: i32, beta: i32, empties: u32, passed: bool) -> i32 {
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+        if empties == 1 {
+            return self.solve_last(board);
+        }
+        // The opponent keeps its stable discs, which caps the score. Only
+        // worth computing if all of its discs being stable would cut off.
+        let opp = board.get_side_to_move() ^ 1;
+        if empties >= STABILITY_CUTOFF_MIN_EMPTIES && MAX_SCORE - 2 * board.get_num_pieces(opp) <= alpha {
+            let max_score = MAX_SCORE - 2 * board.get_stable_discs(opp).count_ones() as i32;
+            if max_score <= alpha {
+                return max_score;
+            }
+        }
+
+        let moves = self.move_generator.generate_legal_moves(board);
+        if moves == 0 {
+            if passed {
+                return final_score(board);
+            }
+            let mut child = *board;
+            child.switch_side_to_move();
+            return -self.solve_node(&child, -beta, -alpha, empties, true);
+        }
+
+        let side = board.get_side_to_move();
+        let mut best = -MAX_SCORE - 1;
+        if empties < FASTEST_FIRST_MIN_EMPTIES {
+            // Odd quadrants first: the last move in a region is worth more.
+            let parity = parity_mask(board.get_empty_squares());
+            for region in [moves & parity, moves & !parity] {
+                let mut squares = region;
+                while squares != 0 {
+                    let square = squares.trailing_zeros();
+                    squares &= squares - 1;
+                    let mut child = *board;
+                    child.make_move(Move::new(square, side));
+                    let score = -self.solve_node(&child, -beta, -alpha, empties - 1, false);
+                    if self.stopped {
+                        return 0;
+                    }
+                    if score > best {
+                        best = score;
+                        if score > alpha {
+                            alpha = score;
+                            if alpha >= beta {
+                                return best;
+                            }
+                        }
+                    }
+                }
+            }
+            return best;
+        }
+
+        for mv in self.order_moves(board, moves, empties) {
+            let mut child = *board;
+            child.make_move(mv);
+            let score = -self.solve_node(&child, -beta, -alpha, empties - 1, false);
+            if self.stopped {
+                return 0;
+            }
+            if score > best {
+                best = score;
+                if score > alpha {
+                    alpha = score;
+                    if alpha >= beta {
+                        return best;
+                    }
+                }
+            }
+        }
+        return best;
+    }
+
+    // One empty square left: whoever can play it does, and no further
+    // search is needed.
+    fn solve_last(&mut self, board: &Board) -> i32 {
+        let side = board.get_side_to_move();
+        let square = board.get_empty_squares().trailing_zeros();
+        let diff = board.get_num_pieces(side) - board.get_num_pieces(side ^ 1);
+
+        let flips = board.get_flips(square, side).count_ones() as i32;
+        if flips > 0 {
+            return diff + 2 * flips + 1;
+        }
+        let flips = board.get_flips(square, side ^ 1).count_ones() as i32;
+        if flips > 0 {
+            return diff - 2 * flips - 1;
+        }
+        return final_score(board);
+    }
+}
+
+// Disc differential of a finished game from the side to move's view, with
+// the empty squares going to the winner.
+fn final_score(board: &Board) -> i32 {
+    let side = board.get_side_to_move();
+    let diff = board.get_num_pieces(side) - board.get_num_pieces(side ^ 1);
+    let empties = board.get_empty_squares().count_ones() as i32;
+    if diff > 0 {
+        return diff + empties;
+    }
+    if diff < 0 {
+        return diff - empties;
+    }
+    return 0;
+}
+
+// Union of the quadrants holding an odd number of empty squares.
+fn parity_mask(empty: u64) -> u64 {
+    let mut mask = 0;
+    for quadrant in QUADRANTS {
+        if (empty & quadrant).count_ones() % 2 == 1 {
+            mask |= quadrant;
+        }
+    }
+    return mask;
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::seq::IteratorRandom;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::board::{GameStatus, BLACK};
+
+    // Plays random moves from the start until at most `empties` squares
+    // are empty, retrying games that end before that.
+    fn random_position(rng: &mut StdRng, empties: u32) -> Board {
+        let move_generator = MoveGenerator::new();
+        loop {
+            let mut board = Board::std_start_pos(BLACK);
+            while let Some(mv) = move_generator.legal_move_iter(&board).choose(rng) {
+                board.make_move(mv);
+                if board.get_empty_squares().count_ones() <= empties {
+                    if move_generator.legal_move_iter(&board).len() > 0 {
+                        return board;
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    // Plain minimax over the whole remaining game tree.
+    fn minimax(board: &Board) -> i32 {
+        let moves = MoveGenerator::new().legal_move_iter(board);
+        if moves.len() == 0 {
+            let side = board.get_side_to_move();
+            match board.get_game_status() {
+                GameStatus::Finished { score, .. } => return score[side] - score[side ^ 1],
+                status => panic!("no moves in {:?} position", status),
+            }
+        }
+        let mut best = -MAX_SCORE;
+        for mv in moves {
+            let mut child = *board;
+            child.make_move(mv);
+            best = best.max(-minimax(&child));
+        }
+        return best;
+    }
+
+    #[test]
+    fn solve_matches_minimax() {
+        let mut rng = StdRng::seed_from_u64(19);
+        let mut solver = EndgameSolver::new(MoveGenerator::new());
+        // Full trees with 9 or 10 empties are slow to walk, so only one
+        // position in 25 gets that many.
+        for i in 0..300 {
+            let empties = if i % 25 == 0 { rng.gen_range(9..=10) } else { rng.gen_range(1..=8) };
+            let board = random_position(&mut rng, empties);
+            let (mv, score) = solver.solve(&board);
+            assert_eq!(score, minimax(&board), "{}", board);
+
+            let mut child = board;
+            child.make_move(mv.unwrap());
+            assert_eq!(-solver.solve(&child).1, score, "{} {}", board, mv.unwrap());
+        }
+    }
+
+    #[test]
+    fn solve_wld_agrees_with_solve() {
+        let mut rng = StdRng::seed_from_u64(20);
+        let mut solver = EndgameSolver::new(MoveGenerator::new());
+        let mut outcomes = vec![];
+        for _ in 0..200 {
+            let empties = rng.gen_range(1..=12);
+            let board = random_position(&mut rng, empties);
+            let (_, score) = solver.solve(&board);
+            let (mv, outcome) = solver.solve_wld(&board).unwrap();
+            assert_eq!(outcome, Outcome::from_score(score), "{}", board);
+            outcomes.push(outcome);
+            if outcome == Outcome::Loss {
+                continue;
+            }
+            // The opponent cannot do better than the opposite outcome
+            // after the proving move.
+            let mut child = board;
+            child.make_move(mv.unwrap());
+            let reply = Outcome::from_score(solver.solve(&child).1);
+            let expected = if outcome == Outcome::Win { Outcome::Loss } else { Outcome::Draw };
+            assert_eq!(reply, expected, "{} {}", board, mv.unwrap());
+        }
+        for outcome in [Outcome::Win, Outcome::Draw, Outcome::Loss] {
+            assert!(outcomes.contains(&outcome));
+        }
+    }
+
+    #[test]
+    fn stopped_wld_solve_has_no_outcome() {
+        let mut rng = StdRng::seed_from_u64(21);
+        let board = random_position(&mut rng, 14);
+        let mut solver = EndgameSolver::new(MoveGenerator::new());
+        solver.set_max_nodes(Some(100));
+        assert_eq!(solver.solve_wld(&board), None);
+        assert!(solver.is_stopped());
+    }
+}
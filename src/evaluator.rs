@@ -0,0 +1,96 @@
+//! Static evaluation of positions for the search.
+//!
+//! Evaluators score a position from the side to move's view in
+//! `EVAL_SCALE` units per disc, so that scores of different evaluators
+//! and exact endgame scores can be compared.
+
+use crate::board::Board;
+use crate::move_generator::MoveGenerator;
+
+/// Evaluation units per disc of final disc differential.
+pub const EVAL_SCALE: i32 = 128;
+
+/// Classic weighted-square table in `EVAL_SCALE` units: corners are worth
+/// eight discs, X- and C-squares next to them cost two to four.
+pub const SQUARE_WEIGHTS: [i32; 64] = [
+    1024, -256, 128,  64,  64, 128, -256, 1024,
+    -256, -512, -32, -32, -32, -32, -512, -256,
+     128,  -32,  16,  16,  16,  16,  -32,  128,
+      64,  -32,  16,   0,   0,  16,  -32,   64,
+      64,  -32,  16,   0,   0,  16,  -32,   64,
+     128,  -32,  16,  16,  16,  16,  -32,  128,
+    -256, -512, -32, -32, -32, -32, -512, -256,
+    1024, -256, 128,  64,  64, 128, -256, 1024,
+];
+
+/// Scores positions for the search.
+pub trait Evaluator: Send {
+    /// Returns the evaluation of `board` from the side to move's view, in
+    /// `EVAL_SCALE` units per disc.
+    fn evaluate(&self, board: &Board) -> i32;
+}
+
+/// Scores positions by the current disc difference.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DiscCountEvaluator;
+
+impl Evaluator for DiscCountEvaluator {
+    fn evaluate(&self, board: &Board) -> i32 {
+        let side = board.get_side_to_move();
+        return (board.get_num_pieces(side) - board.get_num_pieces(side ^ 1)) * EVAL_SCALE;
+    }
+}
+
+/// Scores positions by summing a weight per occupied square, counted for
+/// own and against opponent discs.
+#[derive(Copy, Clone, Debug)]
+pub struct WeightedSquareEvaluator {
+    weights: [i32; 64],
+}
+
+impl WeightedSquareEvaluator {
+    /// Creates an evaluator with one weight per square.
+    pub fn new(weights: [i32; 64]) -> WeightedSquareEvaluator {
+        return WeightedSquareEvaluator { weights };
+    }
+}
+
+impl Default for WeightedSquareEvaluator {
+    fn default() -> WeightedSquareEvaluator {
+        return WeightedSquareEvaluator::new(SQUARE_WEIGHTS);
+    }
+}
+
+impl Evaluator for WeightedSquareEvaluator {
+    fn evaluate(&self, board: &Board) -> i32 {
+        let side = board.get_side_to_move();
+        return weighted_sum(&self.weights, board.get_side_squares(side))
+            - weighted_sum(&self.weights, board.get_side_squares(side ^ 1));
+    }
+}
+
+/// Scores positions by the difference in legal moves, one move being worth
+/// one disc.
+#[derive(Copy, Clone, Default)]
+pub struct MobilityEvaluator {
+    move_generator: MoveGenerator,
+}
+
+impl Evaluator for MobilityEvaluator {
+    fn evaluate(&self, board: &Board) -> i32 {
+        let own = self.move_generator.generate_legal_moves(board).count_ones() as i32;
+        let mut passed = *board;
+        passed.switch_side_to_move();
+        let opp = self.move_generator.generate_legal_moves(&passed).count_ones() as i32;
+        return (own - opp) * EVAL_SCALE;
+    }
+}
+
+fn weighted_sum(weights: &[i32; 64], mut bitboard: u64) -> i32 {
+    let mut sum = 0;
+    while bitboard != 0 {
+        sum += weights[bitboard.trailing_zeros() as usize];
+        bitboard &= bitboard - 1;
+    }
+    return sum;
+}
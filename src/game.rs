@@ -0,0 +1,195 @@
+//! Game records: a start position plus the moves played from it.
+//!
+//! Games are exchanged as compact transcripts such as `f5d6c3d3c4`, the
+//! squares of every placement in order. Passes are left out since they
+//! follow from the position; `pa` or `pass` is still accepted on import.
+
+use std::fmt;
+
+use crate::board::{Board, GameStatus, MoveError, UndoInfo, BLACK};
+use crate::moves::{Move, ParseMoveError};
+
+/// Reasons a transcript can fail to load as a `Game`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TranscriptError {
+    /// The move with 1-based number `index` is not valid notation.
+    Parse { index: usize, error: ParseMoveError },
+    /// The move with 1-based number `index` is illegal in the position
+    /// reached by the moves before it.
+    Illegal { index: usize, mv: Move, error: MoveError },
+}
+
+impl fmt::Display for TranscriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TranscriptError::Parse { index, error } => write!(f, "move {}: {}", index, error),
+            TranscriptError::Illegal { index, mv, error } => write!(f, "move {} ({}): {}", index, mv, error),
+        }
+    }
+}
+
+impl std::error::Error for TranscriptError {}
+
+/// A game: the start position, the moves played and the current board.
+///
+/// ```
+/// use othello::Game;
+///
+/// let game = Game::from_transcript("f5d6c3d3c4").unwrap();
+/// assert_eq!(game.to_transcript(), "f5d6c3d3c4");
+/// assert!(Game::from_transcript("f5d6a1").is_err());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Game {
+    start_position: Board,
+    board: Board,
+    moves: Vec<Move>,
+    undos: Vec<UndoInfo>,
+}
+
+impl Game {
+    /// Creates a game with no moves played from `start_position`.
+    pub fn new(start_position: Board) -> Game {
+        return Game {
+            start_position,
+            board: start_position,
+            moves: vec![],
+            undos: vec![],
+        };
+    }
+
+    /// Creates a game from the standard start position with black to move.
+    pub fn std_start_pos() -> Game {
+        return Game::new(Board::std_start_pos(BLACK));
+    }
+
+    /// Returns the position the game started from.
+    pub fn get_start_position(&self) -> Board {
+        return self.start_position;
+    }
+
+    /// Returns the current position.
+    pub fn get_board(&self) -> Board {
+        return self.board;
+    }
+
+    /// Returns every move played so far, passes included.
+    pub fn get_moves(&self) -> &[Move] {
+        return &self.moves;
+    }
+
+    /// Plays `mv` if it is legal in the current position.
+    pub fn play(&mut self, mv: Move) -> Result<(), MoveError> {
+        let undo = self.board.try_make_move(mv)?;
+        self.moves.push(mv);
+        self.undos.push(undo);
+        return Ok(());
+    }
+
+    /// Takes back the last move, returning it, or `None` if no move has
+    /// been played.
+    pub fn undo(&mut self) -> Option<Move> {
+        let undo = self.undos.pop()?;
+        self.board.unmake_move(undo);
+        return self.moves.pop();
+    }
+
+    /// Returns the transcript of the game, e.g. `f5d6c3d3c4`.
+    pub fn to_transcript(&self) -> String {
+        let mut transcript = String::new();
+        for mv in self.moves.iter().filter(|mv| !mv.is_pass()) {
+            transcript.push_str(&mv.to_string());
+        }
+        return transcript;
+    }
+
+    /// Replays a transcript from the standard start position.
+    pub fn from_transcript(transcript: &str) -> Result<Game, TranscriptError> {
+        return Game::from_transcript_with_start(Board::std_start_pos(BLACK), transcript);
+    }
+
+    /// Replays a transcript from `start_position`, inserting passes where
+    /// the side to move has no placement. Fails on the first move that
+    /// cannot be parsed or is illegal.
+    pub fn from_transcript_with_start(start_position: Board, transcript: &str) -> Result<Game, TranscriptError> {
+        let mut game = Game::new(start_position);
+        let text: String = transcript.chars().filter(|c| !c.is_whitespace()).collect();
+        let mut rest = text.as_str();
+        let mut index = 0;
+
+        while !rest.is_empty() {
+            index += 1;
+            let len = if rest.to_ascii_lowercase().starts_with("pass") { 4 } else { 2 };
+            let (token, tail) = rest.split_at(rest.char_indices().nth(len).map_or(rest.len(), |(i, _)| i));
+            rest = tail;
+
+            let side = game.board.get_side_to_move();
+            let mut mv = Move::parse(token, side).map_err(|error| TranscriptError::Parse { index, error })?;
+            if let Some(square) = mv.get_square() {
+                if game.board.get_game_status() == GameStatus::MustPass {
+                    game.play(Move::pass(side)).unwrap();
+                    mv = Move::new(square, side ^ 1);
+                }
+            }
+            game.play(mv).map_err(|error| TranscriptError::Illegal { index, mv, error })?;
+        }
+        return Ok(game);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::WHITE;
+    use crate::moves::ParseMoveError;
+
+    // After c1 black has no move and passes before white's a3.
+    const PASS_TRANSCRIPT: &str = "d3c3f5d2d1e1b2c1a3";
+
+    #[test]
+    fn inserts_implicit_passes() {
+        let game = Game::from_transcript(PASS_TRANSCRIPT).unwrap();
+        assert_eq!(game.get_moves().len(), 10);
+        assert_eq!(game.get_moves()[8], Move::pass(BLACK));
+        assert_eq!(game.get_moves()[9], Move::new(16, WHITE));
+        assert_eq!(game.to_transcript(), PASS_TRANSCRIPT);
+
+        for explicit in ["d3c3f5d2d1e1b2c1passa3", "d3 c3 f5 d2 d1 e1 b2 c1 PA a3"] {
+            let with_pass = Game::from_transcript(explicit).unwrap();
+            assert_eq!(with_pass.get_moves(), game.get_moves());
+            assert_eq!(with_pass.get_board(), game.get_board());
+        }
+    }
+
+    #[test]
+    fn reports_errors_with_move_number() {
+        let err = Game::from_transcript("f5pass").unwrap_err();
+        let mv = Move::pass(WHITE);
+        assert_eq!(err, TranscriptError::Illegal { index: 2, mv, error: MoveError::IllegalPass });
+        assert_eq!(err.to_string(), "move 2 (pass): cannot pass while a legal move exists");
+
+        let err = Game::from_transcript("f5d6zzc3").unwrap_err();
+        let error = ParseMoveError::InvalidSquare("zz".to_string());
+        assert_eq!(err, TranscriptError::Parse { index: 3, error });
+
+        let err = Game::from_transcript("f5f5").unwrap_err();
+        assert!(matches!(err, TranscriptError::Illegal { index: 2, error: MoveError::Occupied, .. }));
+    }
+
+    #[test]
+    fn undo_takes_back_moves() {
+        let mut game = Game::from_transcript(PASS_TRANSCRIPT).unwrap();
+        let mut expected = Game::from_transcript("d3c3f5d2d1e1b2c1").unwrap();
+        expected.play(Move::pass(BLACK)).unwrap();
+
+        assert_eq!(game.undo(), Some(Move::new(16, WHITE)));
+        assert_eq!(game.get_board(), expected.get_board());
+        assert_eq!(game.undo(), Some(Move::pass(BLACK)));
+        assert_eq!(game.to_transcript(), "d3c3f5d2d1e1b2c1");
+        while game.undo().is_some() {}
+        assert!(game.get_moves().is_empty());
+        assert_eq!(game.get_board(), game.get_start_position());
+        assert_eq!(game.get_board().get_hash(), Board::std_start_pos(BLACK).get_hash());
+        assert_eq!(game.undo(), None);
+    }
+}
@@ -1,21 +1,23 @@
 use piston::GenericEvent;
 
-use crate::Board;
-use crate::Move;
+use othello::{Game, Move};
 /// Handles events for Sudoku game.
 pub struct GameboardController {
-    /// Stores the gameboard state.
-    pub gameboard: Board,
+    /// Stores the game played on the board.
+    pub game: Game,
     pub selected_cell: Option<[usize; 2]>,
+    /// Ignores clicks on the board while set, e.g. while the bot thinks.
+    pub locked: bool,
     cursor_pos: [f64; 2]
 }
 
 impl GameboardController {
     /// Creates a new gameboard controller.
-    pub fn new(gameboard: Board) -> GameboardController {
+    pub fn new(game: Game) -> GameboardController {
         GameboardController {
-            gameboard: gameboard,
+            game,
             selected_cell: None,
+            locked: false,
             cursor_pos: [0.0; 2],
         }
     }
@@ -27,6 +29,9 @@ impl GameboardController {
         if let Some(pos) = e.mouse_cursor_args() {
             self.cursor_pos = pos;
         }
+        if self.locked {
+            return;
+        }
         if let Some(Button::Mouse(MouseButton::Left)) = e.press_args() {
             // Find coordinates relative to upper left corner.
             let x = self.cursor_pos[0] - pos[0];
@@ -36,7 +41,10 @@ impl GameboardController {
                 // Compute the cell position.
                 let cell_x = (x / size * 8.0) as usize;
                 let cell_y = (y / size * 8.0) as usize;
-                self.gameboard.make_move(Move::new((cell_y * 8 + cell_x) as u32, self.gameboard.side_to_move));
+                let mv = Move::new((cell_y * 8 + cell_x) as u32, self.game.get_board().get_side_to_move());
+                if let Err(err) = self.game.play(mv) {
+                    println!("Illegal move {}: {}", mv, err);
+                }
             }
         }
     }
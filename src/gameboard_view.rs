@@ -3,6 +3,8 @@
 use graphics::types::Color;
 use graphics::{Context, Graphics};
 
+use othello::{BLACK, WHITE};
+
 use crate::gameboard_controller::GameboardController;
 
 /// Stores gameboard view settings.
@@ -52,6 +54,12 @@ impl GameboardViewSettings {
     }
 }
 
+impl Default for GameboardViewSettings {
+    fn default() -> GameboardViewSettings {
+        GameboardViewSettings::new()
+    }
+}
+
 /// Stores visual information about a gameboard.
 pub struct GameboardView {
     /// Stores gameboard view settings.
@@ -62,7 +70,7 @@ impl GameboardView {
     /// Creates a new gameboard view.
     pub fn new(settings: GameboardViewSettings) -> GameboardView {
         GameboardView {
-            settings: settings,
+            settings,
         }
     }
 
@@ -75,7 +83,7 @@ impl GameboardView {
     ) {
         use graphics::{Line, Rectangle};
 
-        let ref settings = self.settings;
+        let settings = &self.settings;
         let board_rect = [
             settings.position[0],
             settings.position[1],
@@ -116,11 +124,12 @@ impl GameboardView {
             
         }
 
+        let gameboard = controller.game.get_board();
         let cell_side = settings.size / 8.0;
         for y in 0..8 {
             for x in 0..8 {
                 let curbit: u64 = 1 << (y * 8 + x);
-                if (controller.gameboard.occ_squares[0] & curbit) != 0 {
+                if (gameboard.get_side_squares(WHITE) & curbit) != 0 {
                     Rectangle::new(settings.white_piece_color).draw(
                         [settings.position[0] + cell_side * (x as f64) + 7.5,
                          settings.position[1] + cell_side * (y as f64) + 7.5, 
@@ -131,7 +140,7 @@ impl GameboardView {
                         g
                     )
                 }
-                else if (controller.gameboard.occ_squares[1] & curbit) != 0 {
+                else if (gameboard.get_side_squares(BLACK) & curbit) != 0 {
                     Rectangle::new(settings.black_piece_color).draw(
                         [settings.position[0] + cell_side * (x as f64) + 7.5,
                          settings.position[1] + cell_side * (y as f64) + 7.5, 
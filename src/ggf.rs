@@ -0,0 +1,287 @@
+//! Reading and writing games in the Generic Game Format (GGF).
+//!
+//! A GGF game is a list of `KEY[value]` properties between `(;` and `;)`,
+//! for example
+//!
+//! ```text
+//! (;GM[Othello]PB[alice]PW[bob]RB[1800.00]RW[1750.00]TI[15:00//02:00]TY[8]RE[+4.000]
+//! BO[8 -------- -------- -------- ---O*--- ---*O--- -------- -------- -------- *]
+//! B[f5//0.01]W[d6]B[c3];)
+//! ```
+//!
+//! The board in `BO` uses `*` for black and `O` for white. Moves are `B[]`
+//! and `W[]` entries holding a square or `PA` for a pass, optionally
+//! followed by `/eval/time`. Only 8x8 board types are supported.
+
+use std::fmt;
+
+use crate::board::{Board, ParseBoardError, BLACK, WHITE};
+use crate::game::{Game, TranscriptError};
+use crate::moves::Move;
+
+/// Reasons a GGF game can fail to load.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GgfError {
+    /// The text is not a well-formed list of GGF properties.
+    Syntax(String),
+    /// The game is not played on a standard 8x8 board.
+    UnsupportedBoard(String),
+    /// The `BO` property does not describe a valid position.
+    Board(ParseBoardError),
+    /// A move cannot be parsed or is illegal.
+    Move(TranscriptError),
+}
+
+impl fmt::Display for GgfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GgfError::Syntax(msg) => write!(f, "GGF syntax error: {}", msg),
+            GgfError::UnsupportedBoard(board_type) => write!(f, "unsupported board type '{}'", board_type),
+            GgfError::Board(err) => write!(f, "invalid board: {}", err),
+            GgfError::Move(err) => write!(f, "invalid move: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for GgfError {}
+
+/// A game read from or written to GGF, with its metadata.
+#[derive(Clone, Debug)]
+pub struct GgfGame {
+    /// Place the game was played, `PC`.
+    pub place: Option<String>,
+    /// Date the game was played, `DT`.
+    pub date: Option<String>,
+    /// Name of the black player, `PB`.
+    pub black_player: Option<String>,
+    /// Name of the white player, `PW`.
+    pub white_player: Option<String>,
+    /// Rating of the black player, `RB`.
+    pub black_rating: Option<f64>,
+    /// Rating of the white player, `RW`.
+    pub white_rating: Option<f64>,
+    /// Time control, `TI`.
+    pub time_control: Option<String>,
+    /// Board type, `TY`, e.g. `8` or `8r`.
+    pub board_type: Option<String>,
+    /// Result from black's view, `RE`, e.g. `+4.000` or `-64.000:r`.
+    pub result: Option<String>,
+    /// Start position and moves.
+    pub game: Game,
+}
+
+impl GgfGame {
+    /// Wraps `game` without any metadata.
+    pub fn new(game: Game) -> GgfGame {
+        return GgfGame {
+            place: None,
+            date: None,
+            black_player: None,
+            white_player: None,
+            black_rating: None,
+            white_rating: None,
+            time_control: None,
+            board_type: None,
+            result: None,
+            game,
+        };
+    }
+
+    /// Parses a single GGF game, validating every move.
+    pub fn parse(text: &str) -> Result<GgfGame, GgfError> {
+        let mut games = GgfGame::parse_all(text)?;
+        if games.len() != 1 {
+            return Err(GgfError::Syntax(format!("expected one game, found {}", games.len())));
+        }
+        return Ok(games.remove(0));
+    }
+
+    /// Parses every GGF game in `text`, as found in server archives with
+    /// one game per line.
+    pub fn parse_all(text: &str) -> Result<Vec<GgfGame>, GgfError> {
+        let mut games = vec![];
+        let mut rest = text;
+        while let Some(start) = rest.find("(;") {
+            let body = &rest[start + 2..];
+            let end = match body.find(";)") {
+                Some(end) => end,
+                None => return Err(GgfError::Syntax("missing ';)' at end of game".to_string())),
+            };
+            games.push(GgfGame::parse_properties(&body[..end])?);
+            rest = &body[end + 2..];
+        }
+        return Ok(games);
+    }
+
+    fn parse_properties(body: &str) -> Result<GgfGame, GgfError> {
+        let mut ggf = GgfGame::new(Game::std_start_pos());
+        let mut moves: Vec<(usize, &str)> = vec![];
+        let mut rest = body.trim_start();
+
+        while !rest.is_empty() {
+            let open = match rest.find('[') {
+                Some(open) => open,
+                None => return Err(GgfError::Syntax(format!("expected '[' after '{}'", rest))),
+            };
+            let key = &rest[..open];
+            let close = match rest[open..].find(']') {
+                Some(close) => open + close,
+                None => return Err(GgfError::Syntax(format!("missing ']' after '{}'", key))),
+            };
+            let value = &rest[open + 1..close];
+            rest = rest[close + 1..].trim_start();
+
+            match key {
+                "GM" if !value.eq_ignore_ascii_case("othello") => {
+                    return Err(GgfError::Syntax(format!("not an Othello game: '{}'", value)));
+                }
+                "PC" => ggf.place = Some(value.to_string()),
+                "DT" => ggf.date = Some(value.to_string()),
+                "PB" => ggf.black_player = Some(value.to_string()),
+                "PW" => ggf.white_player = Some(value.to_string()),
+                "RB" => ggf.black_rating = value.parse().ok(),
+                "RW" => ggf.white_rating = value.parse().ok(),
+                "TI" => ggf.time_control = Some(value.to_string()),
+                "TY" if !value.trim_start_matches('s').starts_with('8') => {
+                    return Err(GgfError::UnsupportedBoard(value.to_string()));
+                }
+                "TY" => ggf.board_type = Some(value.to_string()),
+                "RE" => ggf.result = Some(value.to_string()),
+                "BO" => ggf.game = Game::new(parse_ggf_board(value)?),
+                "B" => moves.push((BLACK, value)),
+                "W" => moves.push((WHITE, value)),
+                _ => {}
+            }
+        }
+
+        for (i, (side, value)) in moves.into_iter().enumerate() {
+            let index = i + 1;
+            let square = value.split('/').next().unwrap_or("");
+            let mv = Move::parse(square, side)
+                .map_err(|error| GgfError::Move(TranscriptError::Parse { index, error }))?;
+            ggf.game.play(mv)
+                .map_err(|error| GgfError::Move(TranscriptError::Illegal { index, mv, error }))?;
+        }
+        return Ok(ggf);
+    }
+}
+
+fn parse_ggf_board(value: &str) -> Result<Board, GgfError> {
+    let mut parts = value.split_whitespace();
+    let size = parts.next().unwrap_or("");
+    if size != "8" {
+        return Err(GgfError::UnsupportedBoard(size.to_string()));
+    }
+    let mut tokens: Vec<&str> = parts.collect();
+    let side = tokens.pop().unwrap_or("");
+    let position = format!("{} {}", tokens.concat(), side);
+    return position.parse().map_err(GgfError::Board);
+}
+
+/// Writes the game as a single line of GGF.
+impl fmt::Display for GgfGame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(;GM[Othello]")?;
+        let text_properties = [
+            ("PC", &self.place),
+            ("DT", &self.date),
+            ("PB", &self.black_player),
+            ("PW", &self.white_player),
+        ];
+        for (key, value) in text_properties {
+            if let Some(value) = value {
+                write!(f, "{}[{}]", key, value)?;
+            }
+        }
+        if let Some(rating) = self.black_rating {
+            write!(f, "RB[{:.2}]", rating)?;
+        }
+        if let Some(rating) = self.white_rating {
+            write!(f, "RW[{:.2}]", rating)?;
+        }
+        if let Some(time_control) = &self.time_control {
+            write!(f, "TI[{}]", time_control)?;
+        }
+        write!(f, "TY[{}]", self.board_type.as_deref().unwrap_or("8"))?;
+        if let Some(result) = &self.result {
+            write!(f, "RE[{}]", result)?;
+        }
+
+        let position = self.game.get_start_position().to_string().replace('X', "*");
+        write!(f, "BO[8")?;
+        for row in 0..8 {
+            write!(f, " {}", &position[row * 8..row * 8 + 8])?;
+        }
+        write!(f, " {}]", &position[65..])?;
+
+        for mv in self.game.get_moves() {
+            let color = if mv.get_side() == BLACK { "B" } else { "W" };
+            let square = if mv.is_pass() { "PA".to_string() } else { mv.to_string().to_uppercase() };
+            write!(f, "{}[{}]", color, square)?;
+        }
+        return write!(f, ";)");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::MoveError;
+
+    // Black captures on c1, white has to pass and black plays on.
+    const SERVER_LINE: &str = "(;GM[Othello]PC[NIOS]DT[2003.12.15_13:24:03.MST]PB[alice]PW[bob]RB[1821.21]RW[1750.00]\
+        TI[05:00//02:00]TY[8]RE[+64.000]\
+        BO[8 *O------ -------- -------- -------- -------- -------- -------- -----OO* *]\
+        B[c1//0.01]W[PA]B[E8/64.00/1.25];)";
+
+    #[test]
+    fn parses_server_game() {
+        let ggf = GgfGame::parse(SERVER_LINE).unwrap();
+        assert_eq!(ggf.place.as_deref(), Some("NIOS"));
+        assert_eq!(ggf.black_player.as_deref(), Some("alice"));
+        assert_eq!(ggf.white_player.as_deref(), Some("bob"));
+        assert_eq!(ggf.black_rating, Some(1821.21));
+        assert_eq!(ggf.time_control.as_deref(), Some("05:00//02:00"));
+        assert_eq!(ggf.result.as_deref(), Some("+64.000"));
+
+        let start = ggf.game.get_start_position();
+        assert_eq!(start.get_side_to_move(), BLACK);
+        assert_eq!(start.get_side_squares(BLACK), 0x8000000000000001);
+        let moves: Vec<String> = ggf.game.get_moves().iter().map(|mv| mv.to_string()).collect();
+        assert_eq!(moves, ["c1", "pass", "e8"]);
+        assert_eq!(ggf.game.get_board().get_num_pieces(WHITE), 0);
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let ggf = GgfGame::parse(SERVER_LINE).unwrap();
+        let text = ggf.to_string();
+        let parsed = GgfGame::parse(&text).unwrap();
+        assert_eq!(parsed.to_string(), text);
+        assert_eq!(parsed.date, ggf.date);
+        assert_eq!(parsed.white_rating, ggf.white_rating);
+        assert_eq!(parsed.game.get_start_position(), ggf.game.get_start_position());
+        assert_eq!(parsed.game.get_moves(), ggf.game.get_moves());
+
+        let game = Game::from_transcript("f5d6c3d3c4f4f6f3e6e7").unwrap();
+        let parsed = GgfGame::parse(&GgfGame::new(game.clone()).to_string()).unwrap();
+        assert_eq!(parsed.game.get_moves(), game.get_moves());
+    }
+
+    #[test]
+    fn rejects_invalid_games() {
+        let err = GgfGame::parse("(;GM[Chess]TY[8]B[f5];)").unwrap_err();
+        assert_eq!(err, GgfError::Syntax("not an Othello game: 'Chess'".to_string()));
+
+        let err = GgfGame::parse("(;GM[Othello]TY[10]B[f5];)").unwrap_err();
+        assert_eq!(err, GgfError::UnsupportedBoard("10".to_string()));
+
+        let err = GgfGame::parse("(;GM[Othello]TY[8]B[f5]").unwrap_err();
+        assert_eq!(err, GgfError::Syntax("missing ';)' at end of game".to_string()));
+
+        let err = GgfGame::parse("(;GM[Othello]TY[8]B[f5]W[d6]B[a1];)").unwrap_err();
+        let expected = TranscriptError::Illegal { index: 3, mv: Move::new(0, BLACK), error: MoveError::NoFlips };
+        assert_eq!(err, GgfError::Move(expected));
+        assert_eq!(err.to_string(), "invalid move: move 3 (a1): move does not flip any discs");
+    }
+}
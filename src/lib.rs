@@ -0,0 +1,51 @@
+//! Othello engine: bitboard positions, legal move generation and an
+//! alpha-beta search.
+//!
+//! ```
+//! use othello::{Board, MoveGenerator, Search, BLACK};
+//!
+//! let mut board = Board::std_start_pos(BLACK);
+//! let move_gen = MoveGenerator::new();
+//! assert_eq!(move_gen.generate_legal_moves(&board).count_ones(), 4);
+//!
+//! let mut search = Search::new(board, move_gen);
+//! search.find_best_move(board, 4);
+//! board.make_move(search.get_best_move().unwrap());
+//! assert_eq!(board.get_num_pieces(BLACK), 4);
+//! ```
+
+#![allow(clippy::needless_return)]
+
+pub mod board;
+pub mod classical;
+pub mod endgame;
+pub mod evaluator;
+pub mod game;
+pub mod ggf;
+pub mod move_generator;
+pub mod moves;
+pub mod pattern;
+pub mod perft;
+pub mod search;
+mod stop;
+pub mod symmetry;
+pub mod training;
+pub mod transposition;
+pub mod wthor;
+pub mod zobrist;
+
+pub use crate::board::{bitboard_to_string, Board, BoardPositions, GameStatus, MoveError, ParseBoardError, UndoInfo, BLACK, WHITE};
+pub use crate::classical::{ClassicalEvaluator, Term, TermScore};
+pub use crate::endgame::{EndgameSolver, Outcome};
+pub use crate::evaluator::{DiscCountEvaluator, Evaluator, MobilityEvaluator, WeightedSquareEvaluator, EVAL_SCALE};
+pub use crate::game::{Game, TranscriptError};
+pub use crate::ggf::{GgfError, GgfGame};
+pub use crate::move_generator::{MoveGenerator, MoveIter, OrderedMoveIter};
+pub use crate::moves::{parse_square, square_to_string, Move, ParseMoveError};
+pub use crate::pattern::{PatternEvaluator, WeightsError};
+pub use crate::perft::{perft, perft_divide};
+pub use crate::search::{MoveOrdering, Search, SearchLimits};
+pub use crate::symmetry::Symmetry;
+pub use crate::training::{fit_pattern_weights, samples_from_game, self_play, TrainingConfig, TrainingSample};
+pub use crate::transposition::{Bound, TTEntry, TranspositionTable};
+pub use crate::wthor::{WthorError, WthorGame, WthorReader};
@@ -0,0 +1,186 @@
+//! Legal move generation.
+
+use crate::board::{get_dir_mask, Board};
+use crate::moves::Move;
+
+/// Generates legal moves for a `Board`.
+#[derive(Copy, Clone)]
+pub struct MoveGenerator {}
+
+impl MoveGenerator {
+
+    /// Creates a new move generator.
+    pub fn new() -> MoveGenerator {
+        return MoveGenerator {};
+    }
+
+    /// Returns the bitboard of squares where the side to move can play.
+    pub fn generate_legal_moves(&self, board: &Board) -> u64 {
+        let mut legal_moves: u64 = 0;
+        for direction in 0..8 {
+            legal_moves |= self.generate_moves_in_dir(board, direction);
+        }
+        return legal_moves;
+    }
+
+
+    /// Returns true if the side to move has no placement but the opponent
+    /// does, making a pass the only legal move.
+    pub fn must_pass(&self, board: &Board) -> bool {
+        if self.generate_legal_moves(board) != 0 {
+            return false;
+        }
+        let mut passed = *board;
+        passed.switch_side_to_move();
+        return self.generate_legal_moves(&passed) != 0;
+    }
+
+    /// Iterates over every legal move for the side to move without
+    /// allocating. Yields a single pass if the side has to pass, and
+    /// nothing once the game is over.
+    pub fn legal_move_iter(&self, board: &Board) -> MoveIter {
+        let side = board.get_side_to_move();
+        let moves = self.generate_legal_moves(board);
+        if moves == 0 && self.must_pass(board) {
+            return MoveIter::pass(side);
+        }
+        return MoveIter::new(moves, side);
+    }
+
+    /// Returns every legal move for the side to move, a single pass if
+    /// the side has to pass, or no moves at all once the game is over.
+    pub fn generate_legal_move_list(&self, board: &Board) -> Vec<Move> {
+        return self.legal_move_iter(board).collect();
+    }
+
+    fn generate_moves_in_dir(&self, board: &Board, direction: usize) -> u64 {
+        let opp = board.get_side_squares(board.get_side_to_move() ^ 1) & get_dir_mask(direction);
+        let own = board.get_side_squares(board.get_side_to_move());
+        let mut p_atts = opp & board.shift_bitboard_in_dir(own, direction);
+        p_atts |= opp & board.shift_bitboard_in_dir(p_atts, direction);
+        p_atts |= opp & board.shift_bitboard_in_dir(p_atts, direction);
+        p_atts |= opp & board.shift_bitboard_in_dir(p_atts, direction);
+        p_atts |= opp & board.shift_bitboard_in_dir(p_atts, direction);
+        p_atts |= opp & board.shift_bitboard_in_dir(p_atts, direction);
+
+        return board.get_empty_squares() & board.shift_bitboard_in_dir(p_atts, direction);
+    }
+
+
+    /// Converts a bitboard of target squares into moves for `side`.
+    pub fn conv_move_bitboard2vec(&self, bitboard: u64, side: usize) -> Vec<Move> {
+        return MoveIter::new(bitboard, side).collect();
+    }
+}
+
+impl Default for MoveGenerator {
+    fn default() -> MoveGenerator {
+        return MoveGenerator::new();
+    }
+}
+
+/// Iterates over the moves in a bitboard of target squares, lowest
+/// square first, by bit-scanning.
+#[derive(Copy, Clone, Debug)]
+pub struct MoveIter {
+    bitboard: u64,
+    side: usize,
+    pass: bool
+}
+
+impl MoveIter {
+    /// Creates an iterator over the squares of `bitboard` for `side`.
+    pub fn new(bitboard: u64, side: usize) -> MoveIter {
+        return MoveIter { bitboard, side, pass: false };
+    }
+
+    /// Creates an iterator yielding only a pass by `side`.
+    pub fn pass(side: usize) -> MoveIter {
+        return MoveIter { bitboard: 0, side, pass: true };
+    }
+
+    /// Returns the remaining moves sorted by `score`, highest first.
+    pub fn ordered<F: FnMut(Move) -> i32>(self, mut score: F) -> OrderedMoveIter {
+        let mut ordered = OrderedMoveIter {
+            squares: [0; MAX_MOVES],
+            scores: [0; MAX_MOVES],
+            len: 0,
+            side: self.side
+        };
+        for mv in self {
+            ordered.squares[ordered.len] = mv.get_square().unwrap_or(PASS_SQUARE) as u8;
+            ordered.scores[ordered.len] = score(mv);
+            ordered.len += 1;
+        }
+        return ordered;
+    }
+}
+
+impl Iterator for MoveIter {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        if self.pass {
+            self.pass = false;
+            return Some(Move::pass(self.side));
+        }
+        if self.bitboard == 0 {
+            return None;
+        }
+        let square = self.bitboard.trailing_zeros();
+        self.bitboard &= self.bitboard - 1;
+        return Some(Move::new(square, self.side));
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.bitboard.count_ones() as usize + self.pass as usize;
+        return (len, Some(len));
+    }
+}
+
+impl ExactSizeIterator for MoveIter {}
+
+const MAX_MOVES: usize = 64;
+const PASS_SQUARE: u32 = 64;
+
+/// Yields moves highest score first, as built by `MoveIter::ordered`.
+/// Scores are computed once up front and the next move is selected
+/// lazily, so a cutoff after the first few moves skips most of the
+/// sorting.
+#[derive(Copy, Clone, Debug)]
+pub struct OrderedMoveIter {
+    squares: [u8; MAX_MOVES],
+    scores: [i32; MAX_MOVES],
+    len: usize,
+    side: usize
+}
+
+impl Iterator for OrderedMoveIter {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        if self.len == 0 {
+            return None;
+        }
+        let mut best = 0;
+        for i in 1..self.len {
+            if self.scores[i] > self.scores[best] {
+                best = i;
+            }
+        }
+        let square = self.squares[best] as u32;
+        self.len -= 1;
+        self.squares[best] = self.squares[self.len];
+        self.scores[best] = self.scores[self.len];
+        if square == PASS_SQUARE {
+            return Some(Move::pass(self.side));
+        }
+        return Some(Move::new(square, self.side));
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        return (self.len, Some(self.len));
+    }
+}
+
+impl ExactSizeIterator for OrderedMoveIter {}
@@ -0,0 +1,160 @@
+//! Moves as played on a `Board`.
+//!
+//! Squares are written in algebraic notation: a file letter `a`-`h` for
+//! the column from left to right followed by a rank `1`-`8` for the row
+//! from top to bottom, so square `0` is `a1` and square `63` is `h8`.
+
+use std::fmt;
+
+use crate::symmetry::Symmetry;
+
+/// Reasons a string can fail to parse as a `Move`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseMoveError {
+    /// The text is neither a square like `f5` nor `pass`.
+    InvalidSquare(String),
+}
+
+impl fmt::Display for ParseMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseMoveError::InvalidSquare(text) => {
+                write!(f, "invalid move '{}', expected a square from a1 to h8 or 'pass'", text)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseMoveError {}
+
+/// Returns the algebraic name of `square`, e.g. `f5` for square 37.
+pub fn square_to_string(square: u32) -> String {
+    let file = (b'a' + (square % 8) as u8) as char;
+    let rank = square / 8 + 1;
+    return format!("{}{}", file, rank);
+}
+
+/// Parses an algebraic square name such as `f5` or `F5` into its index.
+pub fn parse_square(text: &str) -> Result<u32, ParseMoveError> {
+    let bytes = text.as_bytes();
+    if bytes.len() != 2 {
+        return Err(ParseMoveError::InvalidSquare(text.to_string()));
+    }
+    let file = bytes[0].to_ascii_lowercase();
+    let rank = bytes[1];
+    if !(b'a'..=b'h').contains(&file) || !(b'1'..=b'8').contains(&rank) {
+        return Err(ParseMoveError::InvalidSquare(text.to_string()));
+    }
+    return Ok((rank - b'1') as u32 * 8 + (file - b'a') as u32);
+}
+
+/// A move by `side`: either a disc placement or a pass.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Move {
+    /// Places a disc of `side` on `square`.
+    Place { square: u32, side: usize },
+    /// `side` has no legal placement and hands the turn to the opponent.
+    Pass { side: usize },
+}
+
+impl Move {
+    /// Creates a move placing a disc of `side` on `square`.
+    pub fn new(square: u32, side: usize) -> Move {
+        return Move::Place {
+            square,
+            side
+        };
+    }
+
+    /// Creates a pass by `side`.
+    pub fn pass(side: usize) -> Move {
+        return Move::Pass { side };
+    }
+
+    /// Returns the side making the move.
+    pub fn get_side(&self) -> usize {
+        match *self {
+            Move::Place { side, .. } => side,
+            Move::Pass { side } => side,
+        }
+    }
+
+    /// Returns the square a disc is placed on, or `None` for a pass.
+    pub fn get_square(&self) -> Option<u32> {
+        match *self {
+            Move::Place { square, .. } => Some(square),
+            Move::Pass { .. } => None,
+        }
+    }
+
+    /// Parses a move for `side` from a square such as `f5`, or from `pass`
+    /// (also `pa` or `ps`) for a pass.
+    pub fn parse(text: &str, side: usize) -> Result<Move, ParseMoveError> {
+        let text = text.trim();
+        match text.to_ascii_lowercase().as_str() {
+            "pass" | "pa" | "ps" => return Ok(Move::pass(side)),
+            _ => {}
+        }
+        return Ok(Move::new(parse_square(text)?, side));
+    }
+
+    /// Returns the move transformed by `symmetry`. Passes are unchanged.
+    pub fn transform(&self, symmetry: Symmetry) -> Move {
+        match *self {
+            Move::Place { square, side } => Move::new(symmetry.apply_square(square), side),
+            Move::Pass { side } => Move::pass(side),
+        }
+    }
+
+    /// Returns true if the move is a pass.
+    pub fn is_pass(&self) -> bool {
+        return matches!(self, Move::Pass { .. });
+    }
+}
+
+/// Formats the move as its square, e.g. `f5`, or as `pass`.
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Move::Place { square, .. } => write!(f, "{}", square_to_string(square)),
+            Move::Pass { .. } => write!(f, "pass"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BLACK, WHITE};
+
+    #[test]
+    fn squares_round_trip() {
+        assert_eq!(square_to_string(0), "a1");
+        assert_eq!(square_to_string(37), "f5");
+        assert_eq!(square_to_string(63), "h8");
+        for square in 0..64 {
+            let name = square_to_string(square);
+            assert_eq!(parse_square(&name), Ok(square));
+            assert_eq!(parse_square(&name.to_uppercase()), Ok(square));
+            assert_eq!(Move::parse(&name, BLACK).unwrap().to_string(), name);
+        }
+    }
+
+    #[test]
+    fn parses_moves_and_passes() {
+        assert_eq!(Move::parse("F5", BLACK), Ok(Move::new(37, BLACK)));
+        assert_eq!(Move::parse(" d6\n", WHITE), Ok(Move::new(43, WHITE)));
+        for text in ["pa", "PA", "ps", "pass", "Pass"] {
+            assert_eq!(Move::parse(text, WHITE), Ok(Move::pass(WHITE)), "{}", text);
+        }
+        assert_eq!(Move::pass(BLACK).to_string(), "pass");
+    }
+
+    #[test]
+    fn rejects_invalid_squares() {
+        for text in ["i1", "a9", "a0", "a", "a10", "", "5f"] {
+            assert_eq!(parse_square(text), Err(ParseMoveError::InvalidSquare(text.to_string())), "{}", text);
+            assert!(Move::parse(text, BLACK).is_err(), "{}", text);
+        }
+    }
+}
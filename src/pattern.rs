@@ -0,0 +1,412 @@
+//! Pattern-based position evaluation.
+//!
+//! The evaluation is a sum of table lookups: every pattern is a fixed
+//! list of squares, and each way of filling those squares with empty, own
+//! and opponent discs has its own weight. Patterns are the edges with
+//! their X-squares, the 3x3 and 2x5 corner blocks and the diagonals of
+//! length 4 to 8, each taken in all of its symmetric placements. Weights
+//! depend on the stage of the game, the number of discs on the board.
+//!
+//! Weight files start with the magic `OTHW`, the format version, the
+//! number of stages and of pattern families and the table size of each
+//! family, all as little-endian `u32`. The weights follow as little-endian
+//! `i16`, stage by stage and family by family.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use crate::board::Board;
+use crate::evaluator::{Evaluator, EVAL_SCALE, SQUARE_WEIGHTS};
+use crate::symmetry::Symmetry;
+
+/// Number of game stages with their own weights.
+pub const N_STAGES: usize = 16;
+
+/// Version of the weight file format written by `PatternEvaluator::save`.
+pub const WEIGHTS_VERSION: u32 = 1;
+
+const WEIGHTS_MAGIC: &[u8; 4] = b"OTHW";
+
+// Squares of one placement of each pattern family. Digit `k` of a pattern
+// index is the content of the `k`-th square.
+const FAMILIES: [&[u32]; 8] = [
+    // a1-h1 edge with the b2 and g2 X-squares.
+    &[0, 1, 2, 3, 4, 5, 6, 7, 9, 14],
+    // 3x3 block in the a1 corner.
+    &[0, 1, 2, 8, 9, 10, 16, 17, 18],
+    // 2x5 block in the a1 corner along the first row.
+    &[0, 1, 2, 3, 4, 8, 9, 10, 11, 12],
+    // Diagonals from a1-h8 down to e1-h4.
+    &[0, 9, 18, 27, 36, 45, 54, 63],
+    &[1, 10, 19, 28, 37, 46, 55],
+    &[2, 11, 20, 29, 38, 47],
+    &[3, 12, 21, 30, 39],
+    &[4, 13, 22, 31],
+];
+
+/// Reasons loading a weight file can fail.
+#[derive(Debug)]
+pub enum WeightsError {
+    /// The underlying reader or writer failed.
+    Io(io::Error),
+    /// The file does not start with the weight file magic.
+    InvalidHeader,
+    /// The file was written in a format version this build cannot read.
+    UnsupportedVersion(u32),
+    /// The stages or pattern families differ from this build's.
+    ShapeMismatch,
+    /// The file ends before all weights were read.
+    Truncated,
+}
+
+impl fmt::Display for WeightsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WeightsError::Io(err) => write!(f, "I/O error: {}", err),
+            WeightsError::InvalidHeader => write!(f, "not a weight file"),
+            WeightsError::UnsupportedVersion(version) => write!(f, "unsupported weight file version {}", version),
+            WeightsError::ShapeMismatch => write!(f, "weight file has different patterns or stages"),
+            WeightsError::Truncated => write!(f, "weight file is truncated"),
+        }
+    }
+}
+
+impl std::error::Error for WeightsError {}
+
+impl From<io::Error> for WeightsError {
+    fn from(err: io::Error) -> WeightsError {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => return WeightsError::Truncated,
+            _ => return WeightsError::Io(err),
+        }
+    }
+}
+
+// One placement of a pattern family on the board.
+struct PatternInstance {
+    family: usize,
+    squares: Vec<u32>,
+}
+
+// The pattern placements and where each family's table starts within a
+// stage.
+struct Layout {
+    instances: Vec<PatternInstance>,
+    family_offsets: Vec<usize>,
+    stage_len: usize,
+}
+
+impl Layout {
+    fn get() -> &'static Layout {
+        static LAYOUT: OnceLock<Layout> = OnceLock::new();
+        return LAYOUT.get_or_init(|| {
+            let mut instances: Vec<PatternInstance> = vec![];
+            let mut family_offsets = vec![];
+            let mut stage_len = 0;
+            for (family, squares) in FAMILIES.iter().enumerate() {
+                family_offsets.push(stage_len);
+                stage_len += 3usize.pow(squares.len() as u32);
+                let mut seen = vec![];
+                for symmetry in Symmetry::ALL {
+                    let image: Vec<u32> = squares.iter().map(|&square| symmetry.apply_square(square)).collect();
+                    let mask = image.iter().fold(0u64, |mask, &square| mask | (1 << square));
+                    if !seen.contains(&mask) {
+                        seen.push(mask);
+                        instances.push(PatternInstance { family, squares: image });
+                    }
+                }
+            }
+            return Layout { instances, family_offsets, stage_len };
+        });
+    }
+}
+
+/// Returns the weight stage of `board`, from 0 at the start to
+/// `N_STAGES - 1` with the board full.
+pub fn get_stage(board: &Board) -> usize {
+    let discs = board.get_occ_squares().count_ones() as usize;
+    return (discs.saturating_sub(4) * N_STAGES / 61).min(N_STAGES - 1);
+}
+
+/// Evaluates positions with stage-dependent pattern weights. Cloning is
+/// cheap since the weight tables are shared.
+///
+/// ```
+/// use othello::{Board, Evaluator, PatternEvaluator, BLACK};
+///
+/// let evaluator = PatternEvaluator::builtin();
+/// assert_eq!(evaluator.evaluate(&Board::std_start_pos(BLACK)), 0);
+/// ```
+#[derive(Clone)]
+pub struct PatternEvaluator {
+    weights: Arc<Vec<i16>>,
+}
+
+impl PatternEvaluator {
+    /// Returns the evaluator with the built-in weights. They score every
+    /// square by a fixed table early on and drift to the plain disc count
+    /// as the board fills up.
+    pub fn builtin() -> PatternEvaluator {
+        static BUILTIN: OnceLock<Arc<Vec<i16>>> = OnceLock::new();
+        let weights = BUILTIN.get_or_init(|| Arc::new(builtin_weights()));
+        return PatternEvaluator { weights: weights.clone() };
+    }
+
+    /// Creates an evaluator from a flat weight table laid out as in the
+    /// weight file.
+    pub fn from_weights(weights: Vec<i16>) -> Result<PatternEvaluator, WeightsError> {
+        if weights.len() != PatternEvaluator::weights_len() {
+            return Err(WeightsError::ShapeMismatch);
+        }
+        return Ok(PatternEvaluator { weights: Arc::new(weights) });
+    }
+
+    /// Returns the number of weights across all stages and patterns.
+    pub fn weights_len() -> usize {
+        return N_STAGES * Layout::get().stage_len;
+    }
+
+    /// Returns the flat weight table.
+    pub fn get_weights(&self) -> &[i16] {
+        return &self.weights;
+    }
+
+    /// Returns the number of pattern placements, which is the number of
+    /// weights every evaluation adds up.
+    pub fn placements_len() -> usize {
+        return Layout::get().instances.len();
+    }
+
+    /// Appends the flat table index of every weight the evaluation of
+    /// `board` adds up to `indices`, e.g. to fit the weights. Indices fit
+    /// in `u32` since they are below `weights_len`.
+    pub fn weight_indices(board: &Board, indices: &mut Vec<u32>) {
+        for_each_weight_index(board, |index| indices.push(index as u32));
+    }
+
+    /// Loads the weight file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<PatternEvaluator, WeightsError> {
+        return PatternEvaluator::read_from(BufReader::new(File::open(path)?));
+    }
+
+    /// Loads the weight file at `path`, falling back to the built-in
+    /// weights if it does not exist. Any other error is returned, so that
+    /// a stale or corrupt file is not silently ignored.
+    pub fn load_or_builtin<P: AsRef<Path>>(path: P) -> Result<PatternEvaluator, WeightsError> {
+        match PatternEvaluator::load(path) {
+            Err(WeightsError::Io(err)) if err.kind() == io::ErrorKind::NotFound => {
+                return Ok(PatternEvaluator::builtin());
+            }
+            result => return result,
+        }
+    }
+
+    /// Reads a weight file from `reader`.
+    pub fn read_from<R: Read>(mut reader: R) -> Result<PatternEvaluator, WeightsError> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != WEIGHTS_MAGIC {
+            return Err(WeightsError::InvalidHeader);
+        }
+        let version = read_u32(&mut reader)?;
+        if version != WEIGHTS_VERSION {
+            return Err(WeightsError::UnsupportedVersion(version));
+        }
+        if read_u32(&mut reader)? as usize != N_STAGES || read_u32(&mut reader)? as usize != FAMILIES.len() {
+            return Err(WeightsError::ShapeMismatch);
+        }
+        for squares in FAMILIES {
+            if read_u32(&mut reader)? != 3u32.pow(squares.len() as u32) {
+                return Err(WeightsError::ShapeMismatch);
+            }
+        }
+
+        let mut bytes = vec![0u8; PatternEvaluator::weights_len() * 2];
+        reader.read_exact(&mut bytes)?;
+        let weights = bytes.chunks_exact(2).map(|pair| i16::from_le_bytes([pair[0], pair[1]])).collect();
+        return PatternEvaluator::from_weights(weights);
+    }
+
+    /// Writes the weights to the file at `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), WeightsError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        return Ok(());
+    }
+
+    /// Writes the weights in the weight file format to `writer`.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), WeightsError> {
+        writer.write_all(WEIGHTS_MAGIC)?;
+        writer.write_all(&WEIGHTS_VERSION.to_le_bytes())?;
+        writer.write_all(&(N_STAGES as u32).to_le_bytes())?;
+        writer.write_all(&(FAMILIES.len() as u32).to_le_bytes())?;
+        for squares in FAMILIES {
+            writer.write_all(&3u32.pow(squares.len() as u32).to_le_bytes())?;
+        }
+        for weight in self.weights.iter() {
+            writer.write_all(&weight.to_le_bytes())?;
+        }
+        return Ok(());
+    }
+}
+
+impl Evaluator for PatternEvaluator {
+    fn evaluate(&self, board: &Board) -> i32 {
+        let mut score = 0;
+        for_each_weight_index(board, |index| score += self.weights[index] as i32);
+        return score;
+    }
+}
+
+// Calls `f` with the flat table index of every weight the evaluation of
+// `board` adds up, one per pattern placement.
+fn for_each_weight_index<F: FnMut(usize)>(board: &Board, mut f: F) {
+    let layout = Layout::get();
+    let stage_offset = get_stage(board) * layout.stage_len;
+    let own = board.get_side_squares(board.get_side_to_move());
+    let opp = board.get_side_squares(board.get_side_to_move() ^ 1);
+    for instance in layout.instances.iter() {
+        f(stage_offset + layout.family_offsets[instance.family] + pattern_index(&instance.squares, own, opp));
+    }
+}
+
+// Index of the contents of `squares` in their family's table: each square
+// is a base-3 digit, 0 for empty, 1 for own and 2 for opponent discs.
+fn pattern_index(squares: &[u32], own: u64, opp: u64) -> usize {
+    let mut index = 0;
+    for &square in squares.iter().rev() {
+        index = index * 3 + ((own >> square) & 1) as usize + 2 * ((opp >> square) & 1) as usize;
+    }
+    return index;
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, WeightsError> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    return Ok(u32::from_le_bytes(buf));
+}
+
+// Spreads each square's value evenly over the patterns covering it, so
+// the evaluation is a weighted square count blending into the disc count.
+fn builtin_weights() -> Vec<i16> {
+    let layout = Layout::get();
+    let mut coverage = [0; 64];
+    for instance in layout.instances.iter() {
+        for &square in instance.squares.iter() {
+            coverage[square as usize] += 1;
+        }
+    }
+
+    let mut weights = vec![0i16; PatternEvaluator::weights_len()];
+    for stage in 0..N_STAGES {
+        let endgame = stage as f64 / (N_STAGES - 1) as f64;
+        for (family, squares) in FAMILIES.iter().enumerate() {
+            // Squares of the canonical placement; every placement of a
+            // family covers squares with the same values and coverage.
+            let values: Vec<f64> = squares.iter().map(|&square| {
+                let value = SQUARE_WEIGHTS[square as usize] as f64 * (1.0 - endgame) + EVAL_SCALE as f64 * endgame;
+                return value / coverage[square as usize] as f64;
+            }).collect();
+            let offset = stage * layout.stage_len + layout.family_offsets[family];
+            for index in 0..3usize.pow(squares.len() as u32) {
+                let mut rest = index;
+                let mut weight = 0.0;
+                for value in values.iter() {
+                    match rest % 3 {
+                        1 => weight += value,
+                        2 => weight -= value,
+                        _ => {}
+                    }
+                    rest /= 3;
+                }
+                weights[offset + index] = weight.round() as i16;
+            }
+        }
+    }
+    return weights;
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::seq::IteratorRandom;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::board::BLACK;
+    use crate::move_generator::MoveGenerator;
+
+    fn weight_file(evaluator: &PatternEvaluator) -> Vec<u8> {
+        let mut bytes = vec![];
+        evaluator.write_to(&mut bytes).unwrap();
+        return bytes;
+    }
+
+    #[test]
+    fn weight_file_round_trips() {
+        let weights = (0..PatternEvaluator::weights_len()).map(|i| (i % 2001) as i16 - 1000).collect();
+        let evaluator = PatternEvaluator::from_weights(weights).unwrap();
+        let bytes = weight_file(&evaluator);
+        assert_eq!(bytes.len(), 4 * (4 + FAMILIES.len()) + 2 * PatternEvaluator::weights_len());
+        let read = PatternEvaluator::read_from(&bytes[..]).unwrap();
+        assert_eq!(read.get_weights(), evaluator.get_weights());
+    }
+
+    #[test]
+    fn rejects_invalid_weight_files() {
+        let mut bytes = weight_file(&PatternEvaluator::builtin());
+        let header_len = 4 * (4 + FAMILIES.len());
+
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = b'X';
+        assert!(matches!(PatternEvaluator::read_from(&wrong_magic[..]), Err(WeightsError::InvalidHeader)));
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[4..8].copy_from_slice(&(WEIGHTS_VERSION + 1).to_le_bytes());
+        let result = PatternEvaluator::read_from(&wrong_version[..]);
+        assert!(matches!(result, Err(WeightsError::UnsupportedVersion(version)) if version == WEIGHTS_VERSION + 1));
+
+        let mut wrong_shape = bytes.clone();
+        wrong_shape[8..12].copy_from_slice(&(N_STAGES as u32 + 1).to_le_bytes());
+        assert!(matches!(PatternEvaluator::read_from(&wrong_shape[..]), Err(WeightsError::ShapeMismatch)));
+
+        assert!(matches!(PatternEvaluator::read_from(&bytes[..2]), Err(WeightsError::Truncated)));
+        assert!(matches!(PatternEvaluator::read_from(&bytes[..header_len - 2]), Err(WeightsError::Truncated)));
+        bytes.truncate(bytes.len() - 1);
+        assert!(matches!(PatternEvaluator::read_from(&bytes[..]), Err(WeightsError::Truncated)));
+    }
+
+    #[test]
+    fn builtin_evaluation_is_symmetric() {
+        let evaluator = PatternEvaluator::builtin();
+        let move_generator = MoveGenerator::new();
+        let mut rng = StdRng::seed_from_u64(21);
+        for _ in 0..20 {
+            let mut board = Board::std_start_pos(BLACK);
+            while let Some(mv) = move_generator.legal_move_iter(&board).choose(&mut rng) {
+                board.make_move(mv);
+                let score = evaluator.evaluate(&board);
+                for symmetry in Symmetry::ALL {
+                    assert_eq!(evaluator.evaluate(&board.transform(symmetry)), score, "{} {:?}", board, symmetry);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn only_missing_weight_file_falls_back_to_builtin() {
+        let dir = std::env::temp_dir().join(format!("othello-weights-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("weights.bin");
+        let builtin = PatternEvaluator::builtin();
+        assert_eq!(PatternEvaluator::load_or_builtin(&path).unwrap().get_weights(), builtin.get_weights());
+
+        std::fs::write(&path, &weight_file(&builtin)[..100]).unwrap();
+        assert!(matches!(PatternEvaluator::load_or_builtin(&path), Err(WeightsError::Truncated)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
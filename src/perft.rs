@@ -0,0 +1,87 @@
+//! Perft: counting the positions at a fixed depth of the game tree.
+//!
+//! Passes count as a ply, and a finished game reached before the target
+//! depth counts as a single leaf. With these conventions the counts from
+//! the standard start position match the published reference values
+//! 4, 12, 56, 244, 1396, 8200, 55092, 390216, 3005288, 24571284 and
+//! 212258800 for depths 1 to 11.
+
+use crate::board::Board;
+use crate::move_generator::MoveGenerator;
+use crate::moves::Move;
+
+/// Returns the number of leaf nodes `depth` plies below `board`.
+///
+/// ```
+/// use othello::{perft, Board, BLACK};
+///
+/// let mut board = Board::std_start_pos(BLACK);
+/// let counts: Vec<u64> = (1..=6).map(|depth| perft(&mut board, depth)).collect();
+/// assert_eq!(counts, [4, 12, 56, 244, 1396, 8200]);
+/// ```
+pub fn perft(board: &mut Board, depth: u32) -> u64 {
+    return perft_node(&MoveGenerator::new(), board, depth);
+}
+
+/// Returns the perft count below each legal move of `board`.
+pub fn perft_divide(board: &mut Board, depth: u32) -> Vec<(Move, u64)> {
+    let move_generator = MoveGenerator::new();
+    let mut counts = vec![];
+    if depth == 0 {
+        return counts;
+    }
+    for mv in move_generator.legal_move_iter(board) {
+        let undo = board.make_move(mv);
+        counts.push((mv, perft_node(&move_generator, board, depth - 1)));
+        board.unmake_move(undo);
+    }
+    return counts;
+}
+
+fn perft_node(move_generator: &MoveGenerator, board: &mut Board, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = move_generator.legal_move_iter(board);
+    if moves.len() == 0 {
+        return 1;
+    }
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for mv in moves {
+        let undo = board.make_move(mv);
+        nodes += perft_node(move_generator, board, depth - 1);
+        board.unmake_move(undo);
+    }
+    return nodes;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BLACK, WHITE};
+
+    #[test]
+    fn matches_reference_counts() {
+        let mut board = Board::std_start_pos(BLACK);
+        let counts: Vec<u64> = (1..=9).map(|depth| perft(&mut board, depth)).collect();
+        assert_eq!(counts, [4, 12, 56, 244, 1396, 8200, 55092, 390216, 3005288]);
+        assert_eq!(board, Board::std_start_pos(BLACK));
+    }
+
+    #[test]
+    fn counts_passes_and_finished_games() {
+        // White has to pass after each of black's two captures, and the
+        // game ends once black has made both.
+        let mut board: Board = "XO-----------------------------------------------------------OOX O".parse().unwrap();
+        assert_eq!(perft_divide(&mut board, 1), [(Move::pass(WHITE), 1)]);
+        assert_eq!(perft(&mut board, 2), 2);
+        assert_eq!(perft(&mut board, 3), 2);
+        assert_eq!(perft(&mut board, 4), 2);
+        // The finished games count once however deep the search goes.
+        assert_eq!(perft(&mut board, 8), 2);
+    }
+}
@@ -0,0 +1,540 @@
+//! Alpha-beta search with iterative deepening.
+
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::board::{Board, GameStatus};
+use crate::evaluator::{DiscCountEvaluator, Evaluator, EVAL_SCALE};
+use crate::endgame::{EndgameSolver, Outcome, DEFAULT_ENDGAME_EMPTIES, DEFAULT_WLD_EMPTIES};
+use crate::move_generator::MoveGenerator;
+use crate::moves::Move;
+use crate::stop::StopCondition;
+use crate::transposition::{Bound, TranspositionTable};
+
+/// Default transposition table size in megabytes.
+pub const DEFAULT_TT_SIZE_MB: usize = 16;
+
+// Ordering prior per square: corners first, edges next, and the X- and
+// C-squares next to empty corners last.
+const SQUARE_PRIORS: [i32; 64] = [
+    100, -20, 10,  5,  5, 10, -20, 100,
+    -20, -50, -2, -2, -2, -2, -50, -20,
+     10,  -2,  1,  1,  1,  1,  -2,  10,
+      5,  -2,  1,  0,  0,  1,  -2,   5,
+      5,  -2,  1,  0,  0,  1,  -2,   5,
+     10,  -2,  1,  1,  1,  1,  -2,  10,
+    -20, -50, -2, -2, -2, -2, -50, -20,
+    100, -20, 10,  5,  5, 10, -20, 100,
+];
+
+// Remaining depth from which opponent mobility is used for ordering.
+// Closer to the leaves it costs more than it saves.
+const MOBILITY_ORDERING_MIN_DEPTH: u32 = 3;
+
+/// Selects the heuristics used to order moves in the alpha-beta search.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MoveOrdering {
+    /// Try the transposition table move, or at the root the previous
+    /// iteration's best move, first.
+    pub hash_move: bool,
+    /// Prefer moves leaving the opponent few replies.
+    pub mobility: bool,
+    /// Prefer corners and edges and avoid X- and C-squares.
+    pub square_priors: bool,
+    /// Prefer moves that caused cutoffs elsewhere in the tree.
+    pub history: bool,
+}
+
+impl MoveOrdering {
+    /// Every heuristic enabled.
+    pub fn all() -> MoveOrdering {
+        return MoveOrdering { hash_move: true, mobility: true, square_priors: true, history: true };
+    }
+
+    /// Moves in square order.
+    pub fn none() -> MoveOrdering {
+        return MoveOrdering { hash_move: false, mobility: false, square_priors: false, history: false };
+    }
+}
+
+impl Default for MoveOrdering {
+    fn default() -> MoveOrdering {
+        return MoveOrdering::all();
+    }
+}
+
+/// Deepest iteration a search runs when no depth limit is set.
+pub const MAX_SEARCH_DEPTH: u32 = 64;
+
+/// Limits on how long `Search::find_best_move_with_limits` runs. The
+/// search stops at whichever limit is reached first; unset limits do not
+/// apply.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    /// Deepest iteration to search.
+    pub depth: Option<u32>,
+    /// Wall-clock budget for the whole search.
+    pub time: Option<Duration>,
+    /// Maximum number of nodes to visit.
+    pub nodes: Option<u64>,
+}
+
+impl SearchLimits {
+    /// Limits the search to `depth` plies.
+    pub fn depth(depth: u32) -> SearchLimits {
+        return SearchLimits { depth: Some(depth), ..SearchLimits::default() };
+    }
+
+    /// Limits the search to `time` of wall-clock time.
+    pub fn time(time: Duration) -> SearchLimits {
+        return SearchLimits { time: Some(time), ..SearchLimits::default() };
+    }
+
+    /// Limits the search to `nodes` nodes.
+    pub fn nodes(nodes: u64) -> SearchLimits {
+        return SearchLimits { nodes: Some(nodes), ..SearchLimits::default() };
+    }
+}
+
+/// Finds the best move for the side to move in a position.
+pub struct Search {
+    board: Board,
+    move_generator: MoveGenerator,
+    best_move_this_iter: Option<Move>,
+    best_move: Option<Move>,
+    best_eval_this_iter: i32,
+    best_eval: i32,
+    nodes: u64,
+    tt: TranspositionTable,
+    ordering: MoveOrdering,
+    history: [[i32; 64]; 2],
+    limits: SearchLimits,
+    start_time: Instant,
+    stop: StopCondition,
+    can_stop: bool,
+    stopped: bool,
+    completed_depth: u32,
+    endgame: EndgameSolver,
+    endgame_empties: u32,
+    wld_empties: u32,
+    solved: bool,
+    outcome: Option<Outcome>,
+    evaluator: Box<dyn Evaluator>
+}
+
+impl Search {
+    /// Creates a new search over `board`.
+    pub fn new(board: Board, move_generator: MoveGenerator) -> Search {
+        let best_move_this_iter = None;
+        let best_move = None;
+        let best_eval_this_iter = i32::MIN;
+        let best_eval = i32::MIN;
+        return Search{
+            board, 
+            move_generator,
+            best_move_this_iter,
+            best_move,
+            best_eval_this_iter,
+            best_eval,
+            nodes: 0,
+            tt: TranspositionTable::new(DEFAULT_TT_SIZE_MB),
+            ordering: MoveOrdering::default(),
+            history: [[0; 64]; 2],
+            limits: SearchLimits::default(),
+            start_time: Instant::now(),
+            stop: StopCondition::default(),
+            can_stop: false,
+            stopped: false,
+            completed_depth: 0,
+            endgame: EndgameSolver::new(move_generator),
+            endgame_empties: DEFAULT_ENDGAME_EMPTIES,
+            wld_empties: DEFAULT_WLD_EMPTIES,
+            solved: false,
+            outcome: None,
+            evaluator: Box::new(DiscCountEvaluator)
+        };
+    }
+
+    /// Sets a flag that stops the search when another thread sets it. The
+    /// search then returns the best move of the last completed iteration.
+    pub fn set_stop_flag(&mut self, stop_flag: Arc<AtomicBool>) {
+        self.stop.set_stop_flag(Some(stop_flag));
+    }
+
+    /// Solves positions with at most `empties` empty squares exactly
+    /// instead of searching them to a fixed depth. Zero disables the
+    /// endgame solver.
+    pub fn set_endgame_empties(&mut self, empties: u32) {
+        self.endgame_empties = empties;
+    }
+
+    /// Solves positions with at most `empties` empty squares for their
+    /// win/loss/draw outcome, playing the proving move of a win or draw.
+    /// Lost positions are searched as usual. Zero disables the WLD solve.
+    pub fn set_wld_empties(&mut self, empties: u32) {
+        self.wld_empties = empties;
+    }
+
+    /// Evaluates positions with `evaluator`, the disc count by default.
+    /// Clears the transposition table since its scores came from the old
+    /// evaluator.
+    pub fn set_evaluator(&mut self, evaluator: Box<dyn Evaluator>) {
+        self.evaluator = evaluator;
+        self.tt.clear();
+    }
+
+    /// Sets the move ordering heuristics, e.g. to compare node counts.
+    pub fn set_move_ordering(&mut self, ordering: MoveOrdering) {
+        self.ordering = ordering;
+    }
+
+    /// Replaces the transposition table with an empty one of `size_mb`
+    /// megabytes. A size of zero disables it.
+    pub fn set_tt_size(&mut self, size_mb: usize) {
+        self.tt = TranspositionTable::new(size_mb);
+    }
+
+    fn evaluate_position(&self) -> i32 {
+        return self.evaluator.evaluate(&self.board);
+    }
+
+    fn evaluate_terminal(&self, status: GameStatus) -> i32 {
+        match status {
+            GameStatus::Finished { winner: Some(side), .. } if side == self.board.get_side_to_move() => i32::MAX,
+            GameStatus::Finished { winner: Some(_), .. } => i32::MIN + 1,
+            _ => 0,
+        }
+    }
+
+    /// Searches `board` to `target_depth` plies, storing the result in
+    /// `get_best_move` and `get_best_eval`.
+    pub fn find_best_move(&mut self, board: Board, target_depth: u32) {
+        self.find_best_move_with_limits(board, SearchLimits::depth(target_depth));
+    }
+
+    /// Searches `board` with iterative deepening until a limit in `limits`
+    /// is reached or the stop flag is set. The result is that of the last
+    /// completed iteration; the first iteration always completes.
+    pub fn find_best_move_with_limits(&mut self, board: Board, limits: SearchLimits) {
+
+        self.board = board;
+        self.best_move = None;
+        self.best_eval = i32::MIN + 1;
+        self.nodes = 0;
+        self.history = [[0; 64]; 2];
+        self.set_limits(limits);
+        self.stopped = false;
+        self.completed_depth = 0;
+        self.solved = false;
+        self.outcome = None;
+        
+        let empties = board.get_empty_squares().count_ones();
+        let target_depth = limits.depth.unwrap_or(MAX_SEARCH_DEPTH);
+        for search_depth in 1..=target_depth {
+            self.can_stop = search_depth > 1;
+            self.best_move_this_iter = None;
+            self.best_eval_this_iter = i32::MIN;
+            self.search_depth(i32::MIN + 1, i32::MAX, search_depth, 0);
+            if self.stopped {
+                break;
+            }
+            self.completed_depth = search_depth;
+            if self.best_move_this_iter.is_some() {
+                self.best_move = self.best_move_this_iter;
+                self.best_eval = self.best_eval_this_iter;
+            }
+            // Stop requests made during the first iteration, or too few
+            // nodes into a later one to be seen, are honoured here.
+            if self.stop.limit_reached(self.nodes) {
+                self.stopped = true;
+                break;
+            }
+            // The first iteration leaves a move to fall back on if the
+            // solve is stopped.
+            if empties <= self.endgame_empties {
+                self.solve_endgame(empties);
+                break;
+            }
+            if search_depth == 1 && empties <= self.wld_empties {
+                self.solve_wld_root();
+                if self.stopped || self.outcome != Some(Outcome::Loss) {
+                    break;
+                }
+            }
+            // The next iteration takes several times longer than this one,
+            // so it is unlikely to finish in the remaining budget.
+            if let Some(time) = limits.time {
+                if self.start_time.elapsed() * 2 > time {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Returns the depth of the last completed iteration. After an
+    /// endgame solve this is the number of empty squares.
+    pub fn get_completed_depth(&self) -> u32 {
+        return self.completed_depth;
+    }
+
+    /// Returns true if the last search solved the position exactly, making
+    /// `get_best_eval` the final disc differential in `EVAL_SCALE` units.
+    pub fn is_solved(&self) -> bool {
+        return self.solved;
+    }
+
+    /// Returns the game-theoretic outcome for the side to move if the last
+    /// search solved it, exactly or by a WLD solve.
+    pub fn get_outcome(&self) -> Option<Outcome> {
+        return self.outcome;
+    }
+
+    /// Solves `board` for its win/loss/draw outcome within `limits`,
+    /// ignoring the depth limit. Returns the outcome with the move proving
+    /// it, or `None` if a limit stopped the solve.
+    ///
+    /// ```
+    /// use othello::{Board, MoveGenerator, Outcome, Search, SearchLimits, BLACK};
+    ///
+    /// let board: Board = "XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXOOOOOOOOOOOOOOOOOOOOOOOOOOOOOO-X X".parse().unwrap();
+    /// let mut search = Search::new(board, MoveGenerator::new());
+    /// let (outcome, mv) = search.solve_wld(board, SearchLimits::default()).unwrap();
+    /// assert_eq!(outcome, Outcome::Win);
+    /// assert_eq!(mv.unwrap().to_string(), "g8");
+    /// ```
+    pub fn solve_wld(&mut self, board: Board, limits: SearchLimits) -> Option<(Outcome, Option<Move>)> {
+        self.board = board;
+        self.best_move = None;
+        self.nodes = 0;
+        self.set_limits(limits);
+        self.stopped = false;
+        self.solved = false;
+        self.outcome = None;
+        self.solve_wld_root();
+        return self.outcome.map(|outcome| (outcome, self.best_move));
+    }
+
+    fn solve_wld_root(&mut self) {
+        self.set_endgame_limits();
+        let result = self.endgame.solve_wld(&self.board);
+        self.nodes += self.endgame.get_nodes();
+        let (best_move, outcome) = match result {
+            Some(result) => result,
+            None => {
+                self.stopped = true;
+                return;
+            }
+        };
+        self.outcome = Some(outcome);
+        if outcome != Outcome::Loss {
+            self.best_move = best_move;
+        }
+    }
+
+    fn set_limits(&mut self, limits: SearchLimits) {
+        self.limits = limits;
+        self.start_time = Instant::now();
+        self.stop.set_deadline(limits.time.map(|time| self.start_time + time));
+        self.stop.set_max_nodes(limits.nodes);
+    }
+
+    // The solver counts its nodes from zero, so it gets what is left of
+    // the node limit.
+    fn set_endgame_limits(&mut self) {
+        let mut stop = self.stop.clone();
+        stop.set_max_nodes(self.limits.nodes.map(|nodes| nodes.saturating_sub(self.nodes)));
+        self.endgame.set_stop_condition(stop);
+    }
+
+    fn solve_endgame(&mut self, empties: u32) {
+        self.set_endgame_limits();
+        let (best_move, score) = self.endgame.solve(&self.board);
+        self.nodes += self.endgame.get_nodes();
+        if self.endgame.is_stopped() {
+            return;
+        }
+        self.best_move = best_move;
+        self.best_eval = score * EVAL_SCALE;
+        self.completed_depth = empties;
+        self.solved = true;
+        self.outcome = Some(Outcome::from_score(score));
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if !self.can_stop {
+            return false;
+        }
+        if !self.stopped && self.stop.check(self.nodes) {
+            self.stopped = true;
+        }
+        return self.stopped;
+    }
+
+    /// Returns the best move found by the last search, which is a pass if
+    /// the side to move has no placement. Returns `None` if the game is over.
+    pub fn get_best_move(&self) -> Option<Move> {
+        return self.best_move;
+    }
+
+    /// Returns the evaluation of the best move from the side to move's view,
+    /// in `EVAL_SCALE` units per disc.
+    pub fn get_best_eval(&self) -> i32 {
+        return self.best_eval;
+    }
+
+    /// Returns the number of positions visited by the last search.
+    pub fn get_nodes(&self) -> u64 {
+        return self.nodes;
+    }
+
+    fn score_move(&self, mv: Move, hash_move: Option<Move>, depth: u32) -> i32 {
+        let square = match mv.get_square() {
+            Some(square) => square as usize,
+            None => return 0,
+        };
+        let mut score = 0;
+        if self.ordering.hash_move && Some(mv) == hash_move {
+            score += 1 << 24;
+        }
+        if self.ordering.square_priors {
+            score += SQUARE_PRIORS[square] * 64;
+        }
+        if self.ordering.mobility && depth >= MOBILITY_ORDERING_MIN_DEPTH {
+            let mut child = self.board;
+            child.make_move(mv);
+            score -= self.move_generator.generate_legal_moves(&child).count_ones() as i32 * 256;
+        }
+        if self.ordering.history {
+            score += self.history[mv.get_side()][square].min(1 << 10);
+        }
+        return score;
+    }
+
+    fn search_depth(&mut self, mut alpha: i32, beta: i32, depth: u32, mvs_made: u32) -> i32 {
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+
+        if depth == 0 {
+            return self.evaluate_position();
+        }
+        let hash = self.board.get_hash();
+        let side = self.board.get_side_to_move();
+        let mut tt_move = None;
+        if mvs_made == 0 {
+            tt_move = self.best_move;
+        }
+        if let Some(entry) = self.tt.probe(hash) {
+            tt_move = entry.get_best_move(side).or(tt_move);
+            if mvs_made > 0 && entry.get_depth() >= depth {
+                let score = entry.get_score();
+                match entry.get_bound() {
+                    Bound::Exact => return score.clamp(alpha, beta),
+                    Bound::Lower if score >= beta => return beta,
+                    Bound::Upper if score <= alpha => return alpha,
+                    _ => {}
+                }
+            }
+        }
+
+        let moves = self.move_generator.legal_move_iter(&self.board);
+
+        if moves.len() == 0 {
+            return self.evaluate_terminal(self.board.get_game_status());
+        }
+
+        let mut best_move = None;
+        let ordered_moves = moves.ordered(|mv| self.score_move(mv, tt_move, depth));
+        for mv in ordered_moves {
+            let undo = self.board.make_move(mv);
+            let eval = -self.search_depth(-beta, -alpha, depth - 1, mvs_made + 1);
+            self.board.unmake_move(undo);
+            if self.stopped {
+                return 0;
+            }
+
+            if mvs_made == 0 && (eval > alpha || self.best_move_this_iter.is_none()) {
+                self.best_move_this_iter = Some(mv);
+                self.best_eval_this_iter = eval;
+            }
+            if eval >= beta {
+                if let Some(square) = mv.get_square() {
+                    self.history[side][square as usize] += (depth * depth) as i32;
+                }
+                self.tt.store(hash, depth, Bound::Lower, beta, Some(mv));
+                return beta;
+            }
+            if eval > alpha {
+                alpha = eval;
+                best_move = Some(mv);
+            }
+        }
+        let bound = if best_move.is_some() { Bound::Exact } else { Bound::Upper };
+        self.tt.store(hash, depth, bound, alpha, best_move.or(tt_move));
+        return alpha;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use rand::rngs::StdRng;
+    use rand::seq::IteratorRandom;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::board::BLACK;
+
+    #[test]
+    fn stop_flag_ends_search_after_first_iteration() {
+        let board = Board::std_start_pos(BLACK);
+        let mut search = Search::new(board, MoveGenerator::new());
+        let stop_flag = Arc::new(AtomicBool::new(true));
+        search.set_stop_flag(stop_flag.clone());
+        search.find_best_move_with_limits(board, SearchLimits::depth(8));
+        assert_eq!(search.get_completed_depth(), 1);
+        assert!(search.get_best_move().is_some());
+
+        stop_flag.store(false, Ordering::Relaxed);
+        search.find_best_move_with_limits(board, SearchLimits::depth(4));
+        assert_eq!(search.get_completed_depth(), 4);
+    }
+
+    #[test]
+    fn node_limit_keeps_last_completed_iteration() {
+        let board = Board::std_start_pos(BLACK);
+        let mut search = Search::new(board, MoveGenerator::new());
+        search.find_best_move_with_limits(board, SearchLimits::nodes(2000));
+        assert!(search.get_completed_depth() >= 1);
+        assert!(search.get_completed_depth() < MAX_SEARCH_DEPTH);
+        assert!(search.get_nodes() <= 2000 + MAX_SEARCH_DEPTH as u64);
+        assert!(search.get_best_move().is_some());
+    }
+
+    #[test]
+    fn transposition_table_does_not_change_eval() {
+        let move_generator = MoveGenerator::new();
+        let mut rng = StdRng::seed_from_u64(16);
+        let mut search = Search::new(Board::std_start_pos(BLACK), move_generator);
+        search.set_endgame_empties(0);
+        search.set_wld_empties(0);
+        for _ in 0..40 {
+            let mut board = Board::std_start_pos(BLACK);
+            for _ in 0..rng.gen_range(0..40) {
+                match move_generator.legal_move_iter(&board).choose(&mut rng) {
+                    Some(mv) => board.make_move(mv),
+                    None => break,
+                };
+            }
+            search.set_tt_size(0);
+            search.find_best_move(board, 4);
+            let eval = search.get_best_eval();
+            search.set_tt_size(16);
+            search.find_best_move(board, 4);
+            assert_eq!(search.get_best_eval(), eval, "{}", board);
+        }
+    }
+}
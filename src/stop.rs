@@ -0,0 +1,53 @@
+//! Limits shared by the search and the endgame solver.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+// Nodes between checks of the clock and the stop flag.
+const STOP_CHECK_INTERVAL: u64 = 1024;
+
+/// Decides when a search has to stop: after a number of nodes, at a
+/// deadline or when another thread sets a flag. Unset limits do not apply.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct StopCondition {
+    stop_flag: Option<Arc<AtomicBool>>,
+    deadline: Option<Instant>,
+    max_nodes: Option<u64>,
+}
+
+impl StopCondition {
+    pub(crate) fn set_stop_flag(&mut self, stop_flag: Option<Arc<AtomicBool>>) {
+        self.stop_flag = stop_flag;
+    }
+
+    pub(crate) fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    pub(crate) fn set_max_nodes(&mut self, max_nodes: Option<u64>) {
+        self.max_nodes = max_nodes;
+    }
+
+    /// Returns true if a limit is reached after `nodes` nodes. Called at
+    /// every node: the node limit is checked each time, the clock and the
+    /// flag only every `STOP_CHECK_INTERVAL` nodes.
+    pub(crate) fn check(&self, nodes: u64) -> bool {
+        if self.max_nodes.is_some_and(|max_nodes| nodes >= max_nodes) {
+            return true;
+        }
+        return nodes.is_multiple_of(STOP_CHECK_INTERVAL) && self.limit_reached(nodes);
+    }
+
+    /// Returns true if a limit is reached after `nodes` nodes, checking the
+    /// clock and the flag right away.
+    pub(crate) fn limit_reached(&self, nodes: u64) -> bool {
+        if self.max_nodes.is_some_and(|max_nodes| nodes >= max_nodes) {
+            return true;
+        }
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return true;
+        }
+        return self.stop_flag.as_ref().is_some_and(|stop_flag| stop_flag.load(Ordering::Relaxed));
+    }
+}
@@ -0,0 +1,138 @@
+//! The eight symmetries of the Othello board.
+//!
+//! Every transform is built from three bitboard primitives: flipping the
+//! rows (top to bottom), mirroring the columns (left to right) and
+//! transposing along the a1-h8 diagonal.
+
+/// A rotation or reflection of the board. Rotations are clockwise as the
+/// board is drawn, with a1 in the top-left corner.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// Swaps the top and bottom rows.
+    FlipVertical,
+    /// Swaps the left and right columns.
+    FlipHorizontal,
+    /// Reflects along the a1-h8 diagonal.
+    Transpose,
+    /// Reflects along the h1-a8 diagonal.
+    AntiTranspose,
+}
+
+impl Symmetry {
+    /// All eight symmetries, starting with the identity.
+    pub const ALL: [Symmetry; 8] = [
+        Symmetry::Identity,
+        Symmetry::Rotate90,
+        Symmetry::Rotate180,
+        Symmetry::Rotate270,
+        Symmetry::FlipVertical,
+        Symmetry::FlipHorizontal,
+        Symmetry::Transpose,
+        Symmetry::AntiTranspose,
+    ];
+
+    /// Returns the symmetry that undoes this one.
+    pub fn inverse(&self) -> Symmetry {
+        match *self {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            other => other,
+        }
+    }
+
+    /// Applies the symmetry to every square of `bitboard`.
+    pub fn apply_bitboard(&self, bitboard: u64) -> u64 {
+        match *self {
+            Symmetry::Identity => bitboard,
+            Symmetry::Rotate90 => flip_horizontal(transpose(bitboard)),
+            Symmetry::Rotate180 => flip_vertical(flip_horizontal(bitboard)),
+            Symmetry::Rotate270 => flip_vertical(transpose(bitboard)),
+            Symmetry::FlipVertical => flip_vertical(bitboard),
+            Symmetry::FlipHorizontal => flip_horizontal(bitboard),
+            Symmetry::Transpose => transpose(bitboard),
+            Symmetry::AntiTranspose => flip_vertical(flip_horizontal(transpose(bitboard))),
+        }
+    }
+
+    /// Returns the square `square` is moved to.
+    pub fn apply_square(&self, square: u32) -> u32 {
+        return self.apply_bitboard(1 << square).trailing_zeros();
+    }
+}
+
+fn flip_vertical(bitboard: u64) -> u64 {
+    return bitboard.swap_bytes();
+}
+
+fn flip_horizontal(mut bitboard: u64) -> u64 {
+    let k1: u64 = 0x5555555555555555;
+    let k2: u64 = 0x3333333333333333;
+    let k4: u64 = 0x0F0F0F0F0F0F0F0F;
+    bitboard = ((bitboard >> 1) & k1) | ((bitboard & k1) << 1);
+    bitboard = ((bitboard >> 2) & k2) | ((bitboard & k2) << 2);
+    bitboard = ((bitboard >> 4) & k4) | ((bitboard & k4) << 4);
+    return bitboard;
+}
+
+fn transpose(mut bitboard: u64) -> u64 {
+    let k1: u64 = 0x5500550055005500;
+    let k2: u64 = 0x3333000033330000;
+    let k4: u64 = 0x0F0F0F0F00000000;
+    let mut t = k4 & (bitboard ^ (bitboard << 28));
+    bitboard ^= t ^ (t >> 28);
+    t = k2 & (bitboard ^ (bitboard << 14));
+    bitboard ^= t ^ (t >> 14);
+    t = k1 & (bitboard ^ (bitboard << 7));
+    bitboard ^= t ^ (t >> 7);
+    return bitboard;
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn inverse_undoes_symmetry() {
+        let mut rng = StdRng::seed_from_u64(11);
+        for _ in 0..1000 {
+            let bitboard: u64 = rng.gen();
+            for symmetry in Symmetry::ALL {
+                let transformed = symmetry.apply_bitboard(bitboard);
+                assert_eq!(transformed.count_ones(), bitboard.count_ones());
+                assert_eq!(symmetry.inverse().apply_bitboard(transformed), bitboard, "{:?}", symmetry);
+            }
+        }
+    }
+
+    #[test]
+    fn rotations_are_clockwise() {
+        // a1, h1, h8 and a8 are the top-left, top-right, bottom-right and
+        // bottom-left corners.
+        assert_eq!(Symmetry::Rotate90.apply_square(0), 7);
+        assert_eq!(Symmetry::Rotate90.apply_square(7), 63);
+        assert_eq!(Symmetry::Rotate90.apply_square(63), 56);
+        assert_eq!(Symmetry::Rotate90.apply_square(56), 0);
+        // b1 moves down the h-file to h2.
+        assert_eq!(Symmetry::Rotate90.apply_square(1), 15);
+        assert_eq!(Symmetry::Rotate270.apply_square(15), 1);
+        assert_eq!(Symmetry::Rotate180.apply_square(1), 62);
+    }
+
+    #[test]
+    fn symmetries_are_distinct() {
+        // b1 and c3 together tell every symmetry apart.
+        let bitboard = (1 << 1) | (1 << 18);
+        for (i, a) in Symmetry::ALL.iter().enumerate() {
+            for b in &Symmetry::ALL[i + 1..] {
+                assert_ne!(a.apply_bitboard(bitboard), b.apply_bitboard(bitboard), "{:?} {:?}", a, b);
+            }
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! Fitting pattern weights to the results of played games.
+//!
+//! Every position of a finished game becomes a sample labelled with the
+//! final disc differential from its side to move's view. Games come from
+//! self-play with `Search` or from imported GGF and WTHOR files. The
+//! pattern weights are fitted to the samples by stochastic gradient
+//! descent on the squared error with L2 regularisation.
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::board::{Board, GameStatus};
+use crate::evaluator::EVAL_SCALE;
+use crate::game::Game;
+use crate::move_generator::MoveGenerator;
+use crate::pattern::PatternEvaluator;
+use crate::search::{Search, SearchLimits};
+
+/// A position with the final disc differential of its game from the side
+/// to move's view.
+#[derive(Copy, Clone, Debug)]
+pub struct TrainingSample {
+    pub board: Board,
+    pub score: i32,
+}
+
+/// Settings of `fit_pattern_weights`.
+#[derive(Copy, Clone, Debug)]
+pub struct TrainingConfig {
+    /// Passes over the samples.
+    pub epochs: u32,
+    /// Step size of each update, in discs per disc of error.
+    pub learning_rate: f32,
+    /// L2 penalty pulling every weight towards zero.
+    pub regularisation: f32,
+    /// Seed for shuffling the samples, so runs can be reproduced.
+    pub seed: u64,
+}
+
+impl Default for TrainingConfig {
+    fn default() -> TrainingConfig {
+        return TrainingConfig { epochs: 20, learning_rate: 0.005, regularisation: 0.001, seed: 0 };
+    }
+}
+
+/// Returns a sample for every position of `game` where a disc was placed.
+/// Games that did not reach the end give no samples.
+pub fn samples_from_game(game: &Game) -> Vec<TrainingSample> {
+    let score = match game.get_board().get_game_status() {
+        GameStatus::Finished { score, .. } => score,
+        _ => return vec![],
+    };
+    let mut board = game.get_start_position();
+    let mut samples = vec![];
+    for &mv in game.get_moves() {
+        if !mv.is_pass() {
+            let side = board.get_side_to_move();
+            samples.push(TrainingSample { board, score: score[side] - score[side ^ 1] });
+        }
+        board.make_move(mv);
+    }
+    return samples;
+}
+
+/// Plays `n_games` games of `search` against itself from the standard
+/// start position within `limits` per move. The first `random_moves`
+/// moves of every game are random so that the games differ.
+pub fn self_play<R: Rng>(search: &mut Search, n_games: usize, limits: SearchLimits, random_moves: usize, rng: &mut R) -> Vec<Game> {
+    let move_generator = MoveGenerator::new();
+    let mut games = vec![];
+    for _ in 0..n_games {
+        let mut game = Game::std_start_pos();
+        loop {
+            let board = game.get_board();
+            let mv = if game.get_moves().len() < random_moves {
+                let mut moves = move_generator.legal_move_iter(&board);
+                if moves.len() == 0 {
+                    break;
+                }
+                let index = rng.gen_range(0..moves.len());
+                moves.nth(index).unwrap()
+            }
+            else {
+                search.find_best_move_with_limits(board, limits);
+                match search.get_best_move() {
+                    Some(mv) => mv,
+                    None => break,
+                }
+            };
+            game.play(mv).unwrap();
+        }
+        games.push(game);
+    }
+    return games;
+}
+
+/// Fits pattern weights to `samples` starting from those of `initial`,
+/// e.g. the built-in weights or the last training run's. Calls `progress`
+/// after every epoch with its number and the root mean squared error in
+/// discs.
+pub fn fit_pattern_weights<F: FnMut(u32, f64)>(
+    samples: &[TrainingSample],
+    initial: &PatternEvaluator,
+    config: &TrainingConfig,
+    mut progress: F,
+) -> PatternEvaluator {
+    // Every position uses the same number of weights, one per pattern
+    // placement, so the indices are stored back to back.
+    let stride = PatternEvaluator::placements_len();
+    let mut indices = Vec::with_capacity(samples.len() * stride);
+    for sample in samples {
+        PatternEvaluator::weight_indices(&sample.board, &mut indices);
+    }
+
+    let mut weights: Vec<f32> = initial.get_weights().iter().map(|&weight| weight as f32 / EVAL_SCALE as f32).collect();
+    let mut order: Vec<usize> = (0..samples.len()).collect();
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    for epoch in 1..=config.epochs {
+        order.shuffle(&mut rng);
+        let mut squared_error = 0.0;
+        for &sample in order.iter() {
+            let features = &indices[sample * stride..(sample + 1) * stride];
+            let prediction: f32 = features.iter().map(|&index| weights[index as usize]).sum();
+            let error = samples[sample].score as f32 - prediction;
+            squared_error += (error * error) as f64;
+            for &index in features {
+                let weight = &mut weights[index as usize];
+                *weight += config.learning_rate * (error - config.regularisation * *weight);
+            }
+        }
+        progress(epoch, (squared_error / samples.len().max(1) as f64).sqrt());
+    }
+
+    let weights = weights
+        .iter()
+        .map(|weight| (weight * EVAL_SCALE as f32).round().clamp(i16::MIN as f32, i16::MAX as f32) as i16)
+        .collect();
+    return PatternEvaluator::from_weights(weights).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::seq::IteratorRandom;
+
+    use super::*;
+    use crate::moves::Move;
+
+    fn random_game(rng: &mut StdRng) -> Game {
+        let move_generator = MoveGenerator::new();
+        let mut game = Game::std_start_pos();
+        while let Some(mv) = move_generator.legal_move_iter(&game.get_board()).choose(rng) {
+            game.play(mv).unwrap();
+        }
+        return game;
+    }
+
+    #[test]
+    fn unfinished_games_give_no_samples() {
+        assert!(samples_from_game(&Game::std_start_pos()).is_empty());
+        let game = Game::from_transcript("d3c3f5d2d1e1b2c1a3").unwrap();
+        assert!(samples_from_game(&game).is_empty());
+    }
+
+    #[test]
+    fn samples_are_labelled_with_final_score() {
+        let mut rng = StdRng::seed_from_u64(25);
+        let mut passes = 0;
+        for _ in 0..100 {
+            let game = random_game(&mut rng);
+            let score = match game.get_board().get_game_status() {
+                GameStatus::Finished { score, .. } => score,
+                status => panic!("random game ended with {:?}", status),
+            };
+            let samples = samples_from_game(&game);
+            let placements: Vec<Move> = game.get_moves().iter().copied().filter(|mv| !mv.is_pass()).collect();
+            passes += game.get_moves().len() - placements.len();
+            assert_eq!(samples.len(), placements.len());
+
+            let mut board = game.get_start_position();
+            let mut samples = samples.iter();
+            for &mv in game.get_moves() {
+                if !mv.is_pass() {
+                    let sample = samples.next().unwrap();
+                    let side = board.get_side_to_move();
+                    assert_eq!(sample.board, board);
+                    assert_eq!(sample.score, score[side] - score[side ^ 1]);
+                }
+                board.make_move(mv);
+            }
+        }
+        assert!(passes > 0);
+    }
+
+    #[test]
+    fn fitting_lowers_error_and_round_trips() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let samples: Vec<TrainingSample> = (0..40).flat_map(|_| samples_from_game(&random_game(&mut rng))).collect();
+        let initial = PatternEvaluator::from_weights(vec![0; PatternEvaluator::weights_len()]).unwrap();
+        let config = TrainingConfig { epochs: 5, ..TrainingConfig::default() };
+
+        let mut errors = vec![];
+        let fitted = fit_pattern_weights(&samples, &initial, &config, |epoch, rmse| {
+            assert_eq!(epoch as usize, errors.len() + 1);
+            errors.push(rmse);
+        });
+        assert_eq!(errors.len(), 5);
+        assert!(errors.windows(2).all(|pair| pair[1] < pair[0]), "{:?}", errors);
+        assert!(fitted.get_weights().iter().any(|&weight| weight != 0));
+
+        let mut file = vec![];
+        fitted.write_to(&mut file).unwrap();
+        let read = PatternEvaluator::read_from(file.as_slice()).unwrap();
+        assert_eq!(read.get_weights(), fitted.get_weights());
+    }
+}
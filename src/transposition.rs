@@ -0,0 +1,169 @@
+//! Fixed-size transposition table keyed by `Board::get_hash`.
+
+use crate::moves::Move;
+
+/// How a stored score relates to the true value of the position.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Bound {
+    /// The score is exact.
+    Exact,
+    /// The search failed high; the true value is at least the score.
+    Lower,
+    /// The search failed low; the true value is at most the score.
+    Upper,
+}
+
+const NO_MOVE: u8 = 255;
+const PASS_MOVE: u8 = 64;
+
+/// A search result stored for one position.
+#[derive(Copy, Clone, Debug)]
+pub struct TTEntry {
+    key: u64,
+    score: i32,
+    depth: u8,
+    bound: Bound,
+    best_move: u8,
+}
+
+impl TTEntry {
+    /// Returns the depth the position was searched to.
+    pub fn get_depth(&self) -> u32 {
+        return self.depth as u32;
+    }
+
+    /// Returns the kind of bound `get_score` is.
+    pub fn get_bound(&self) -> Bound {
+        return self.bound;
+    }
+
+    /// Returns the stored score from the side to move's view.
+    pub fn get_score(&self) -> i32 {
+        return self.score;
+    }
+
+    /// Returns the best move found for `side`, if any.
+    pub fn get_best_move(&self, side: usize) -> Option<Move> {
+        match self.best_move {
+            NO_MOVE => None,
+            PASS_MOVE => Some(Move::pass(side)),
+            square => Some(Move::new(square as u32, side)),
+        }
+    }
+}
+
+/// A hash table of `TTEntry`s with a fixed memory budget. Each slot holds
+/// one entry; a new entry replaces the old one unless the old one is for
+/// the same position searched deeper.
+pub struct TranspositionTable {
+    entries: Vec<Option<TTEntry>>,
+}
+
+impl TranspositionTable {
+    /// Creates a table using at most `size_mb` megabytes. A size of zero
+    /// gives a table that stores nothing.
+    pub fn new(size_mb: usize) -> TranspositionTable {
+        let entry_size = std::mem::size_of::<Option<TTEntry>>();
+        let max_entries = size_mb * 1024 * 1024 / entry_size;
+        let len = if max_entries == 0 { 0 } else { 1 << max_entries.ilog2() };
+        return TranspositionTable {
+            entries: vec![None; len],
+        };
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        self.entries.iter_mut().for_each(|entry| *entry = None);
+    }
+
+    /// Returns the number of slots in the table.
+    pub fn get_capacity(&self) -> usize {
+        return self.entries.len();
+    }
+
+    fn index(&self, key: u64) -> usize {
+        return (key as usize) & (self.entries.len() - 1);
+    }
+
+    /// Returns the entry stored for the position with hash `key`.
+    pub fn probe(&self, key: u64) -> Option<TTEntry> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        return self.entries[self.index(key)].filter(|entry| entry.key == key);
+    }
+
+    /// Stores a search result for the position with hash `key`.
+    pub fn store(&mut self, key: u64, depth: u32, bound: Bound, score: i32, best_move: Option<Move>) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let index = self.index(key);
+        if let Some(old) = self.entries[index] {
+            if old.key == key && old.depth as u32 > depth {
+                return;
+            }
+        }
+        let best_move = match best_move {
+            None => NO_MOVE,
+            Some(mv) => mv.get_square().map_or(PASS_MOVE, |square| square as u8),
+        };
+        self.entries[index] = Some(TTEntry {
+            key,
+            score,
+            depth: depth.min(u8::MAX as u32) as u8,
+            bound,
+            best_move,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::BLACK;
+
+    #[test]
+    fn probe_misses_other_key_in_same_slot() {
+        let mut tt = TranspositionTable::new(1);
+        let key = 0x1234_5678_9ABC_DEF0;
+        let other = key + tt.get_capacity() as u64;
+        tt.store(key, 3, Bound::Exact, 42, Some(Move::new(19, BLACK)));
+        assert!(tt.probe(other).is_none());
+        let entry = tt.probe(key).unwrap();
+        assert_eq!((entry.get_depth(), entry.get_bound(), entry.get_score()), (3, Bound::Exact, 42));
+        assert_eq!(entry.get_best_move(BLACK), Some(Move::new(19, BLACK)));
+
+        // A different position takes the slot over.
+        tt.store(other, 1, Bound::Upper, -5, None);
+        assert!(tt.probe(key).is_none());
+        assert_eq!(tt.probe(other).unwrap().get_best_move(BLACK), None);
+    }
+
+    #[test]
+    fn keeps_deeper_entry_for_same_position() {
+        let mut tt = TranspositionTable::new(1);
+        tt.store(7, 6, Bound::Lower, 100, None);
+        tt.store(7, 2, Bound::Exact, 5, None);
+        assert_eq!(tt.probe(7).unwrap().get_depth(), 6);
+        assert_eq!(tt.probe(7).unwrap().get_score(), 100);
+        tt.store(7, 6, Bound::Exact, 80, None);
+        assert_eq!(tt.probe(7).unwrap().get_score(), 80);
+    }
+
+    #[test]
+    fn stores_pass_moves() {
+        let mut tt = TranspositionTable::new(1);
+        tt.store(9, 4, Bound::Exact, 0, Some(Move::pass(BLACK)));
+        assert_eq!(tt.probe(9).unwrap().get_best_move(BLACK), Some(Move::pass(BLACK)));
+    }
+
+    #[test]
+    fn zero_size_stores_nothing() {
+        let mut tt = TranspositionTable::new(0);
+        assert_eq!(tt.get_capacity(), 0);
+        tt.store(9, 4, Bound::Exact, 0, None);
+        assert!(tt.probe(9).is_none());
+        tt.clear();
+    }
+}
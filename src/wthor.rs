@@ -0,0 +1,370 @@
+//! Streaming reader for WTHOR game databases.
+//!
+//! A WTHOR `.wtb` file has a 16-byte header followed by 68-byte game
+//! records. Each record holds the tournament and player numbers, black's
+//! final disc count, the theoretical score and up to 60 moves coded as
+//! `10 * rank + file`, with `0` padding after the last move. Passes are
+//! not stored. Player and tournament names live in `WTHOR.JOU` and
+//! `WTHOR.TRN` as fixed-size Latin-1 records after the same header.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+use crate::board::GameStatus;
+use crate::game::{Game, TranscriptError};
+use crate::moves::Move;
+
+const HEADER_LEN: usize = 16;
+const GAME_RECORD_LEN: usize = 68;
+const PLAYER_RECORD_LEN: usize = 20;
+const TOURNAMENT_RECORD_LEN: usize = 26;
+
+/// Reasons reading a WTHOR file can fail.
+#[derive(Debug)]
+pub enum WthorError {
+    /// The underlying reader failed.
+    Io(io::Error),
+    /// The file header is missing or describes something other than
+    /// 8x8 games.
+    InvalidHeader(String),
+    /// The file ends in the middle of game record `game`.
+    Truncated { game: usize },
+    /// Game record `game` holds a byte that is not a square code.
+    InvalidMove { game: usize, index: usize, code: u8 },
+    /// Game record `game` contains an illegal move.
+    IllegalMove { game: usize, error: TranscriptError },
+}
+
+impl fmt::Display for WthorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WthorError::Io(err) => write!(f, "I/O error: {}", err),
+            WthorError::InvalidHeader(msg) => write!(f, "invalid WTHOR header: {}", msg),
+            WthorError::Truncated { game } => write!(f, "game {}: record is truncated", game),
+            WthorError::InvalidMove { game, index, code } => {
+                write!(f, "game {}: move {} has invalid code {}", game, index, code)
+            }
+            WthorError::IllegalMove { game, error } => write!(f, "game {}: {}", game, error),
+        }
+    }
+}
+
+impl std::error::Error for WthorError {}
+
+impl From<io::Error> for WthorError {
+    fn from(err: io::Error) -> WthorError {
+        return WthorError::Io(err);
+    }
+}
+
+/// The header shared by WTHOR game, player and tournament files.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WthorHeader {
+    /// Date the file was created as `[year, month, day]`.
+    pub created: [u16; 3],
+    /// Number of game records in a `.wtb` file.
+    pub n_games: u32,
+    /// Number of records in a player or tournament file.
+    pub n_records: u16,
+    /// Year the games were played.
+    pub year: u16,
+    /// Board size, `0` or `8` for 8x8.
+    pub board_size: u8,
+    /// Search depth used for the theoretical scores.
+    pub depth: u8,
+}
+
+impl WthorHeader {
+    fn read<R: Read>(reader: &mut R) -> Result<WthorHeader, WthorError> {
+        let mut buf = [0u8; HEADER_LEN];
+        reader.read_exact(&mut buf).map_err(|err| match err.kind() {
+            io::ErrorKind::UnexpectedEof => WthorError::InvalidHeader("file is shorter than 16 bytes".to_string()),
+            _ => WthorError::Io(err),
+        })?;
+        return Ok(WthorHeader {
+            created: [buf[0] as u16 * 100 + buf[1] as u16, buf[2] as u16, buf[3] as u16],
+            n_games: u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]),
+            n_records: u16::from_le_bytes([buf[8], buf[9]]),
+            year: u16::from_le_bytes([buf[10], buf[11]]),
+            board_size: buf[12],
+            depth: buf[14],
+        });
+    }
+}
+
+/// A game from a WTHOR database.
+#[derive(Clone, Debug)]
+pub struct WthorGame {
+    /// Index of the tournament in the tournament file.
+    pub tournament: u16,
+    /// Index of the black player in the player file.
+    pub black_player: u16,
+    /// Index of the white player in the player file.
+    pub white_player: u16,
+    /// Name of the tournament, if the tournament file was loaded.
+    pub tournament_name: Option<String>,
+    /// Name of the black player, if the player file was loaded.
+    pub black_name: Option<String>,
+    /// Name of the white player, if the player file was loaded.
+    pub white_name: Option<String>,
+    /// Black's disc count at the end of the game.
+    pub black_score: u8,
+    /// Black's disc count with perfect play from the recorded depth.
+    pub theoretical_score: u8,
+    /// The replayed game, passes included.
+    pub game: Game,
+}
+
+/// Reads games one record at a time from a WTHOR `.wtb` file.
+pub struct WthorReader<R: Read> {
+    reader: R,
+    header: WthorHeader,
+    players: Vec<String>,
+    tournaments: Vec<String>,
+    next_game: usize,
+    done: bool,
+}
+
+impl WthorReader<BufReader<File>> {
+    /// Opens the `.wtb` file at `path`, loading `WTHOR.JOU` and
+    /// `WTHOR.TRN` from the same directory when they exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<WthorReader<BufReader<File>>, WthorError> {
+        let path = path.as_ref();
+        let mut reader = WthorReader::new(BufReader::new(File::open(path)?))?;
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        if let Ok(file) = File::open(dir.join("WTHOR.JOU")) {
+            reader.players = read_names(BufReader::new(file), PLAYER_RECORD_LEN)?;
+        }
+        if let Ok(file) = File::open(dir.join("WTHOR.TRN")) {
+            reader.tournaments = read_names(BufReader::new(file), TOURNAMENT_RECORD_LEN)?;
+        }
+        return Ok(reader);
+    }
+}
+
+impl<R: Read> WthorReader<R> {
+    /// Reads the header from `reader`, leaving it at the first game.
+    pub fn new(mut reader: R) -> Result<WthorReader<R>, WthorError> {
+        let header = WthorHeader::read(&mut reader)?;
+        if header.board_size != 0 && header.board_size != 8 {
+            return Err(WthorError::InvalidHeader(format!("unsupported board size {}", header.board_size)));
+        }
+        return Ok(WthorReader {
+            reader,
+            header,
+            players: vec![],
+            tournaments: vec![],
+            next_game: 0,
+            done: false,
+        });
+    }
+
+    /// Uses `players` and `tournaments` to fill in names of later games.
+    pub fn with_names(mut self, players: Vec<String>, tournaments: Vec<String>) -> WthorReader<R> {
+        self.players = players;
+        self.tournaments = tournaments;
+        return self;
+    }
+
+    /// Returns the file header.
+    pub fn get_header(&self) -> WthorHeader {
+        return self.header;
+    }
+
+    fn read_game(&mut self) -> Option<Result<WthorGame, WthorError>> {
+        let game_index = self.next_game;
+        let mut buf = [0u8; GAME_RECORD_LEN];
+        let mut filled = 0;
+        while filled < GAME_RECORD_LEN {
+            match self.reader.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(WthorError::Io(err)));
+                }
+            }
+        }
+        self.next_game += 1;
+        if filled < GAME_RECORD_LEN {
+            self.done = true;
+            return Some(Err(WthorError::Truncated { game: game_index }));
+        }
+        return Some(self.parse_game(game_index, &buf));
+    }
+
+    fn parse_game(&self, game_index: usize, buf: &[u8; GAME_RECORD_LEN]) -> Result<WthorGame, WthorError> {
+        let tournament = u16::from_le_bytes([buf[0], buf[1]]);
+        let black_player = u16::from_le_bytes([buf[2], buf[3]]);
+        let white_player = u16::from_le_bytes([buf[4], buf[5]]);
+
+        let mut game = Game::std_start_pos();
+        for (i, &code) in buf[8..].iter().enumerate() {
+            if code == 0 {
+                break;
+            }
+            let (rank, file) = (code / 10, code % 10);
+            if !(1..=8).contains(&rank) || !(1..=8).contains(&file) {
+                return Err(WthorError::InvalidMove { game: game_index, index: i + 1, code });
+            }
+            let square = (rank - 1) as u32 * 8 + (file - 1) as u32;
+
+            let mut side = game.get_board().get_side_to_move();
+            if game.get_board().get_game_status() == GameStatus::MustPass {
+                game.play(Move::pass(side)).unwrap();
+                side ^= 1;
+            }
+            let mv = Move::new(square, side);
+            game.play(mv).map_err(|error| WthorError::IllegalMove {
+                game: game_index,
+                error: TranscriptError::Illegal { index: i + 1, mv, error },
+            })?;
+        }
+
+        return Ok(WthorGame {
+            tournament,
+            black_player,
+            white_player,
+            tournament_name: self.tournaments.get(tournament as usize).cloned(),
+            black_name: self.players.get(black_player as usize).cloned(),
+            white_name: self.players.get(white_player as usize).cloned(),
+            black_score: buf[6],
+            theoretical_score: buf[7],
+            game,
+        });
+    }
+}
+
+/// Yields the game records counted in the header, in order. A corrupt
+/// record is reported as an error and reading continues with the next one.
+/// A file ending before the header's game count or a read failure is
+/// reported once and ends the iteration.
+impl<R: Read> Iterator for WthorReader<R> {
+    type Item = Result<WthorGame, WthorError>;
+
+    fn next(&mut self) -> Option<Result<WthorGame, WthorError>> {
+        if self.done || self.next_game as u64 >= self.header.n_games as u64 {
+            return None;
+        }
+        return self.read_game();
+    }
+}
+
+/// Reads the names from a `WTHOR.JOU` player file.
+pub fn read_players<R: Read>(reader: R) -> Result<Vec<String>, WthorError> {
+    return read_names(reader, PLAYER_RECORD_LEN);
+}
+
+/// Reads the names from a `WTHOR.TRN` tournament file.
+pub fn read_tournaments<R: Read>(reader: R) -> Result<Vec<String>, WthorError> {
+    return read_names(reader, TOURNAMENT_RECORD_LEN);
+}
+
+fn read_names<R: Read>(mut reader: R, record_len: usize) -> Result<Vec<String>, WthorError> {
+    WthorHeader::read(&mut reader)?;
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+
+    let mut names = vec![];
+    for record in data.chunks_exact(record_len) {
+        let len = record.iter().position(|&b| b == 0).unwrap_or(record_len);
+        let name: String = record[..len].iter().map(|&b| b as char).collect();
+        names.push(name.trim_end().to_string());
+    }
+    return Ok(names);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{MoveError, BLACK, WHITE};
+    use crate::moves::parse_square;
+
+    fn header(n_games: u32) -> Vec<u8> {
+        let mut bytes = vec![20, 24, 1, 1];
+        bytes.extend_from_slice(&n_games.to_le_bytes());
+        bytes.extend_from_slice(&[0, 0, 0xE8, 0x07, 8, 0, 22, 0]);
+        return bytes;
+    }
+
+    fn record(transcript: &str, black_score: u8) -> Vec<u8> {
+        let mut bytes = vec![3, 0, 1, 0, 2, 0, black_score, black_score];
+        for i in (0..transcript.len()).step_by(2) {
+            let square = parse_square(&transcript[i..i + 2]).unwrap() as u8;
+            bytes.push((square / 8 + 1) * 10 + square % 8 + 1);
+        }
+        bytes.resize(GAME_RECORD_LEN, 0);
+        return bytes;
+    }
+
+    fn read_all(bytes: &[u8]) -> Vec<Result<WthorGame, WthorError>> {
+        return WthorReader::new(bytes).unwrap().collect();
+    }
+
+    #[test]
+    fn reads_valid_game() {
+        let mut bytes = header(1);
+        bytes.extend(record("f5d6c3d3c4", 33));
+        let games = read_all(&bytes);
+        assert_eq!(games.len(), 1);
+        let game = games[0].as_ref().unwrap();
+        assert_eq!((game.tournament, game.black_player, game.white_player), (3, 1, 2));
+        assert_eq!(game.black_score, 33);
+        assert_eq!(game.game.to_transcript(), "f5d6c3d3c4");
+    }
+
+    #[test]
+    fn inserts_passes() {
+        let mut bytes = header(1);
+        bytes.extend(record("d3c3f5d2d1e1b2c1a3", 10));
+        let game = read_all(&bytes).remove(0).unwrap().game;
+        assert_eq!(game.get_moves().len(), 10);
+        assert_eq!(game.get_moves()[8], Move::pass(BLACK));
+        assert_eq!(game.to_transcript(), "d3c3f5d2d1e1b2c1a3");
+    }
+
+    #[test]
+    fn reports_corrupt_records_and_continues() {
+        let mut bytes = header(3);
+        let mut bad_code = record("f5d6c3", 0);
+        bad_code[9] = 69;
+        bytes.extend(bad_code);
+        bytes.extend(record("f5a1", 0));
+        bytes.extend(record("f5", 0));
+        let games = read_all(&bytes);
+        assert_eq!(games.len(), 3);
+        assert!(matches!(games[0], Err(WthorError::InvalidMove { game: 0, index: 2, code: 69 })));
+        match &games[1] {
+            Err(WthorError::IllegalMove { game: 1, error }) => {
+                let mv = Move::new(0, WHITE);
+                assert_eq!(*error, TranscriptError::Illegal { index: 2, mv, error: MoveError::NoFlips });
+            }
+            other => panic!("expected an illegal move, got {:?}", other),
+        }
+        assert!(games[2].is_ok());
+    }
+
+    #[test]
+    fn reports_truncated_record() {
+        let mut bytes = header(2);
+        bytes.extend(record("f5", 0));
+        bytes.extend(&record("f5d6", 0)[..30]);
+        let games = read_all(&bytes);
+        assert_eq!(games.len(), 2);
+        assert!(matches!(games[1], Err(WthorError::Truncated { game: 1 })));
+    }
+
+    #[test]
+    fn reports_missing_records() {
+        let mut bytes = header(3);
+        bytes.extend(record("f5", 0));
+        bytes.extend(record("f5d6", 0));
+        let games = read_all(&bytes);
+        assert_eq!(games.len(), 3);
+        assert!(games[1].is_ok());
+        assert!(matches!(games[2], Err(WthorError::Truncated { game: 2 })));
+    }
+}
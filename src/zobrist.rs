@@ -0,0 +1,55 @@
+//! Zobrist keys for position hashing.
+//!
+//! The keys are generated at compile time from a fixed seed with
+//! splitmix64, so hashes are stable across runs and builds and can be
+//! stored on disk.
+
+const SEED: u64 = 0x0F0E110D15C5EED5;
+
+/// Key for each side and square, indexed `[side][square]`.
+pub const PIECE_KEYS: [[u64; 64]; 2] = generate_piece_keys();
+
+/// Key xored in while black is to move.
+pub const SIDE_KEY: u64 = splitmix64(!SEED).1;
+
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    return (state, z ^ (z >> 31));
+}
+
+const fn generate_piece_keys() -> [[u64; 64]; 2] {
+    let mut keys = [[0; 64]; 2];
+    let mut state = SEED;
+    let mut i = 0;
+    while i < 128 {
+        let (next_state, key) = splitmix64(state);
+        state = next_state;
+        keys[i / 64][i % 64] = key;
+        i += 1;
+    }
+    return keys;
+}
+
+/// Returns the key xored in when the disc on `square` changes color.
+pub fn flip_key(square: u32) -> u64 {
+    return PIECE_KEYS[0][square as usize] ^ PIECE_KEYS[1][square as usize];
+}
+
+/// Computes the hash of a position from scratch.
+pub fn hash_position(occ_squares: [u64; 2], side_to_move: usize) -> u64 {
+    let mut hash = 0;
+    for (side, &discs) in occ_squares.iter().enumerate() {
+        let mut bitboard = discs;
+        while bitboard != 0 {
+            hash ^= PIECE_KEYS[side][bitboard.trailing_zeros() as usize];
+            bitboard &= bitboard - 1;
+        }
+    }
+    if side_to_move == 1 {
+        hash ^= SIDE_KEY;
+    }
+    return hash;
+}